@@ -0,0 +1,92 @@
+// compares working out rays and normals with the cached inverse matrices against
+// inverting the matrices every time, which is what used to happen for every pixel.
+// Run with `cargo bench --bench transforms`.
+extern crate rtc;
+use rtc::consts::PI;
+use rtc::*;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const SIZE: usize = 500;
+
+fn time<F: FnMut()>(name: &str, count: usize, mut f: F) -> Duration {
+    // once to warm up, then once for real
+    f();
+    let start = Instant::now();
+    f();
+    let elapsed = start.elapsed();
+    println!(
+        "{:<40} {:>8.1} ns each",
+        name,
+        elapsed.as_nanos() as f64 / count as f64
+    );
+    elapsed
+}
+
+// how `Camera::ray_for_pixel` worked before the inverse was cached
+fn ray_for_pixel_inverting(c: &Camera, x: usize, y: usize) -> Ray {
+    let pixel_world_x = c.half_width - (x as Float + 0.5) * c.pixel_size;
+    let pixel_world_y = c.half_height - (y as Float + 0.5) * c.pixel_size;
+    let camera_to_world = c.view_transform().try_inverse().unwrap();
+
+    let pixel_pos = camera_to_world * point(pixel_world_x, pixel_world_y, -1.);
+    let origin = camera_to_world * point(0., 0., 0.);
+    Ray::new(origin, (pixel_pos - origin).normalize())
+}
+
+fn main() {
+    let camera = Camera::new(
+        SIZE,
+        SIZE,
+        PI / 3.,
+        view_transform(point(0., 1.5, -5.), point(0., 1., 0.), vec(0., 1., 0.)),
+    );
+    let pixels = SIZE * SIZE;
+
+    let before = time("rays, inverting per pixel", pixels, || {
+        for y in 0..SIZE {
+            for x in 0..SIZE {
+                black_box(ray_for_pixel_inverting(&camera, x, y));
+            }
+        }
+    });
+    let after = time("rays, cached inverse", pixels, || {
+        for y in 0..SIZE {
+            for x in 0..SIZE {
+                black_box(camera.ray_for_pixel(x, y));
+            }
+        }
+    });
+    println!(
+        "{:<40} {:>8.1}x",
+        "speedup",
+        before.as_secs_f64() / after.as_secs_f64()
+    );
+
+    let sphere = Sphere::new(
+        Transform::identity()
+            .scale(1., 2., 0.5)
+            .rotate_y(0.3)
+            .translate(1., 2., 3.),
+        Material::default(),
+    );
+    let surface_point = sphere.transform().matrix() * point(0., 0.6, 0.8);
+
+    let before = time("normals, transposing per call", pixels, || {
+        for _ in 0..pixels {
+            let world_to_object = black_box(&sphere).world_to_object();
+            let normal = sphere.local_normal_at(world_to_object * surface_point);
+            black_box(normal.transform_by_inverse(&world_to_object).normalize());
+        }
+    });
+    let after = time("normals, cached inverse transpose", pixels, || {
+        for _ in 0..pixels {
+            black_box(black_box(&sphere).normal_at(surface_point));
+        }
+    });
+    println!(
+        "{:<40} {:>8.1}x",
+        "speedup",
+        before.as_secs_f64() / after.as_secs_f64()
+    );
+}
@@ -2,7 +2,11 @@ extern crate rtc;
 use rtc::*;
 extern crate sdl2;
 
-use std::f32::consts::PI;
+use rtc::consts::PI;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::io::Write;
 
 fn create_scene_2(resolution: usize) -> (World, Camera) {
     let red_stripes = Pattern::Stripe(Stripe::new(
@@ -17,42 +21,59 @@ fn create_scene_2(resolution: usize) -> (World, Camera) {
         rotation_y(PI / 2.) * scaling(0.1, 0.1, 0.1),
     ));
 
+    let floor_material = PhongMaterial {
+        pattern: Pattern::Checkerboard(Checkerboard::new(
+            red_stripes,
+            black_stripes,
+            scaling(0.5, 0.5, 0.5),
+        )),
+        specular: 0.,
+        ..Default::default()
+    };
     let mut floor = Plane::xz();
-    floor.material.pattern = Pattern::Checkerboard(Checkerboard::new(
-        red_stripes,
-        black_stripes,
-        scaling(0.5, 0.5, 0.5),
-    ));
-    floor.material.specular = 0.;
+    floor.material = floor_material.clone().into();
 
     let mut left_wall =
         Plane::t(translation(0., 0., 5.) * rotation_y(-PI / 4.) * rotation_x(PI / 2.));
-    left_wall.material = floor.material.clone();
-    left_wall.material.pattern = Pattern::Stripe(Stripe::col(red(), white()));
+    left_wall.material = PhongMaterial {
+        pattern: Pattern::Stripe(Stripe::col(red(), white())),
+        ..floor_material
+    }
+    .into();
 
     let mut right_wall =
         Plane::t(translation(0., 0., 5.) * rotation_y(PI / 4.) * rotation_x(PI / 2.));
-    right_wall.material.pattern = Pattern::Stripe(Stripe::col(red(), white()));
+    right_wall.material = PhongMaterial {
+        pattern: Pattern::Stripe(Stripe::col(red(), white())),
+        ..Default::default()
+    }
+    .into();
 
     let mut middle_sphere = Sphere::pos_r(point(-1.5, 1., 0.5), 1.);
-    middle_sphere.material.pattern =
-        Pattern::SphereMap(SphereMap::col(Pattern::Checkerboard(Checkerboard::new(
+    middle_sphere.material = PhongMaterial {
+        pattern: Pattern::SphereMap(SphereMap::col(Pattern::Checkerboard(Checkerboard::new(
             solid(black()),
             solid(Color::new(0.85, 0.9, 1.1)),
             scaling(0.025, 0.05, 1.),
-        ))));
-    middle_sphere.material.diffuse = 0.7;
-    middle_sphere.material.specular = 0.3;
+        )))),
+        diffuse: 0.7,
+        specular: 0.3,
+        ..Default::default()
+    }
+    .into();
 
+    // a physically based material, for comparison with the Phong spheres
     let mut right_sphere = Sphere::pos_r(point(1.5, 0.5, -0.5), 0.5);
-    right_sphere.material.pattern = solid(Color::new(0.5, 1., 0.1));
-    right_sphere.material.diffuse = 0.7;
-    right_sphere.material.specular = 0.3;
+    right_sphere.material = PbrMaterial::solid(Color::new(0.5, 1., 0.1), 0., 0.4).into();
 
     let mut left_sphere = Sphere::pos_r(point(-1.5, 0.33, -0.75), 0.33);
-    left_sphere.material.pattern = solid(Color::new(1., 0.8, 1.));
-    left_sphere.material.diffuse = 0.7;
-    left_sphere.material.specular = 0.7;
+    left_sphere.material = PhongMaterial {
+        pattern: solid(Color::new(1., 0.8, 1.)),
+        diffuse: 0.7,
+        specular: 0.7,
+        ..Default::default()
+    }
+    .into();
 
     let light = PointLight::new(white(), point(-10., 10., -10.));
 
@@ -77,7 +98,52 @@ fn create_scene_2(resolution: usize) -> (World, Camera) {
     (world, camera)
 }
 
-struct SdlCanvas<'a>(&'a mut sdl2::render::WindowCanvas);
+// the camera circles around the scene while the small green sphere bounces
+// and the light moves across, over `duration` seconds
+fn create_animation_2(duration: Float) -> Animation {
+    let target = point(0., 1., 0.);
+    let up = vec(0., 1., 0.);
+    let orbit = |angle: Float| {
+        let from = point(-5. * angle.sin(), 1.5, -5. * angle.cos());
+        ViewKey::new(from, target, up)
+    };
+    let mut camera = Track::new(orbit(-PI / 6.));
+    // the camera swings from one side to the other and back again
+    for (i, angle) in [0., PI / 6., 0., -PI / 6.].iter().enumerate() {
+        let time = duration * (i + 1) as Float / 4.;
+        camera = camera.key(time, orbit(*angle), Interpolation::Smooth);
+    }
+
+    let sphere_at = |height: Float| {
+        TransformKey::new(
+            vec(1.5, 0.5 + height, -0.5),
+            vec(0., 0., 0.),
+            vec(0.5, 0.5, 0.5),
+        )
+    };
+    let mut sphere = Track::new(sphere_at(0.));
+    for i in 1..=4 {
+        let height = if i % 2 == 1 { 1. } else { 0. };
+        sphere = sphere.key(
+            duration * i as Float / 4.,
+            sphere_at(height),
+            Interpolation::Smooth,
+        );
+    }
+
+    let light = Track::new(point(-10., 10., -10.)).key(
+        duration,
+        point(10., 10., -10.),
+        Interpolation::Linear,
+    );
+
+    Animation::new(24.)
+        .with_camera(camera)
+        .with_object(5, sphere)
+        .with_light(0, light)
+}
+
+struct SdlCanvas<'a>(&'a mut sdl2::render::WindowCanvas, DisplayTransform);
 
 impl SdlCanvas<'_> {
     fn present(&mut self) {
@@ -90,7 +156,7 @@ impl SdlCanvas<'_> {
 
 impl Canvas for SdlCanvas<'_> {
     fn write_pixel(&mut self, c: &Color, x: usize, y: usize) {
-        let (r, g, b) = c.clamp().to_u8();
+        let (r, g, b) = self.1.to_u8(c);
         self.0.set_draw_color(sdl2::pixels::Color::RGB(r, g, b));
         self.0
             .draw_point(sdl2::rect::Point::new(x as i32, y as i32))
@@ -98,7 +164,7 @@ impl Canvas for SdlCanvas<'_> {
     }
 }
 
-fn draw_to_screen(resolution: usize, camera: &Camera, world: &World) {
+fn draw_to_screen(resolution: usize, camera: &Camera, world: &World, display: DisplayTransform) {
     let sdl = sdl2::init().unwrap();
     let video = sdl.video().unwrap();
     let window = video
@@ -106,7 +172,7 @@ fn draw_to_screen(resolution: usize, camera: &Camera, world: &World) {
         .build()
         .unwrap();
     let mut canvas = window.into_canvas().build().unwrap();
-    let mut c = SdlCanvas(&mut canvas);
+    let mut c = SdlCanvas(&mut canvas, display);
     camera.render_to(world, &mut c);
     c.present();
 
@@ -121,15 +187,210 @@ fn draw_to_screen(resolution: usize, camera: &Camera, world: &World) {
     }
 }
 
+// the value following `name` in the command line arguments, if there is one
+fn arg_value(args: &[String], name: &str) -> Option<String> {
+    args.iter()
+        .position(|a| a == name)
+        .and_then(|i| args.get(i + 1))
+        .cloned()
+}
+
 fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
     let resolution: usize = 500;
-    let (world, camera) = create_scene_2(resolution);
+    let (mut world, mut camera) = create_scene_2(resolution);
+
+    // eg `--path-trace 64` to path trace the scene with 64 samples per pixel
+    if let Some(samples) = arg_value(&args, "--path-trace") {
+        camera.integrator = Integrator::PathTracer(PathTracer::default());
+        camera.samples_per_pixel = samples.parse().expect("--path-trace needs a sample count");
+    }
+    // eg `--ao 16` to replace the flat ambient term with 16 ambient occlusion rays per hit
+    if let Some(samples) = arg_value(&args, "--ao") {
+        let samples = samples.parse().expect("--ao needs a sample count");
+        camera.integrator = Integrator::WhittedWithOcclusion(AmbientOcclusion::new(samples, 1.));
+    }
+    if let Some(samples) = arg_value(&args, "--ao-only") {
+        let samples = samples.parse().expect("--ao-only needs a sample count");
+        camera.integrator = Integrator::Occlusion(AmbientOcclusion::new(samples, 1.));
+    }
+    // debug outputs, eg `--aov normal`
+    if let Some(aov) = arg_value(&args, "--aov") {
+        camera.integrator = Integrator::Aov(match aov.as_str() {
+            "depth" => Aov::Depth { max_distance: 10. },
+            "normal" => Aov::Normal,
+            "id" => Aov::ObjectId,
+            "object" => Aov::ObjectPoint,
+            "uv" => Aov::Uv,
+            _ => panic!("--aov should be one of depth, normal, id, object or uv"),
+        });
+    }
+    // what's seen where rays miss everything, eg `--background sky` for daylight with the
+    // sun behind the light, or `--background studio.png` for an equirectangular image
+    if let Some(background) = arg_value(&args, "--background") {
+        world.background = match background.as_str() {
+            "black" => Background::Solid(black()),
+            "gradient" => Background::Gradient {
+                bottom: white(),
+                top: Color::new(0.5, 0.7, 1.),
+            },
+            "sky" => Background::Sky(PhysicalSky::new(vec(-1., 1., -1.), 3.)),
+            filename => {
+                let image = PngCanvas::read_from_file(filename)
+                    .expect("--background should be black, gradient, sky or a png file");
+                Background::Image(EnvironmentMap::from_srgb(
+                    image.buffer(),
+                    Matrix4::identity(),
+                ))
+            }
+        };
+    }
+    if let Some(seed) = arg_value(&args, "--seed") {
+        camera.seed = seed.parse().expect("--seed needs a number");
+    }
+
+    // colors are sRGB encoded by default, eg `--tonemap aces --exposure 0.5` to also
+    // tone map the image, or `--tonemap none` to write out linear values instead
+    let tonemap = arg_value(&args, "--tonemap");
+    let exposure = arg_value(&args, "--exposure")
+        .map(|e| e.parse().expect("--exposure needs a number"))
+        .unwrap_or(0.);
+    let display = match tonemap.as_deref() {
+        Some("none") => DisplayTransform::linear(),
+        None | Some("clamp") => DisplayTransform::srgb(ToneMapper::Clamp, exposure),
+        Some("reinhard") => DisplayTransform::srgb(ToneMapper::Reinhard, exposure),
+        Some("aces") => DisplayTransform::srgb(ToneMapper::AcesFilmic, exposure),
+        Some(_) => panic!("--tonemap should be one of none, clamp, reinhard or aces"),
+    };
 
     if false {
-        draw_to_screen(resolution, &camera, &world)
+        draw_to_screen(resolution, &camera, &world, display)
+    }
+
+    // png files can also be written with `--bit-depth 16`, and with `--alpha` for
+    // a transparent background wherever nothing was hit
+    let png_bit_depth = match arg_value(&args, "--bit-depth").as_deref() {
+        None | Some("8") => PngBitDepth::Eight,
+        Some("16") => PngBitDepth::Sixteen,
+        Some(_) => panic!("--bit-depth should be 8 or 16"),
+    };
+    let output = Output {
+        display,
+        png_bit_depth,
+        alpha: args.iter().any(|a| a == "--alpha"),
+        // image effects applied in order, eg `--post denoise,bloom:1:4:0.5,vignette`
+        post: arg_value(&args, "--post").map(|effects| effects.parse().unwrap()),
+    };
+
+    // eg `--motion-blur 16` to take 16 samples per pixel spread over the time that the
+    // shutter is open (which is 1/48 of a second, or eg `--shutter 0.1` for longer)
+    let shutter = match arg_value(&args, "--motion-blur") {
+        Some(samples) => {
+            let samples = samples.parse().expect("--motion-blur needs a sample count");
+            camera.samples_per_pixel = camera.samples_per_pixel.max(samples);
+            arg_value(&args, "--shutter")
+                .map(|s| s.parse().expect("--shutter needs a number of seconds"))
+                .unwrap_or(1. / 48.)
+        }
+        None => 0.,
+    };
+
+    // eg `--animate 48` to render 2 seconds of a turntable animation, with the
+    // frame number replacing the `#`s in the output filename
+    if let Some(frames) = arg_value(&args, "--animate") {
+        let frames = frames.parse().expect("--animate needs a frame count");
+        let pattern = arg_value(&args, "--output").unwrap_or_else(|| "frame_###.png".to_string());
+        let animation = create_animation_2(frames as Float / 24.).with_shutter(shutter);
+        animation
+            .render_frames(
+                0..frames,
+                &mut world,
+                &mut camera,
+                |frame, world, camera| {
+                    let filename = frame_filename(&pattern, frame);
+                    let format = filename.rsplit('.').next();
+                    let mut w = io::BufWriter::new(fs::File::create(&filename)?);
+                    output.write(output.render(camera, world), format, &mut w)?;
+                    Ok::<(), Box<dyn Error>>(w.flush()?)
+                },
+            )
+            .unwrap();
+        return;
+    }
+
+    // still images with motion blur show the animation while the sphere is rising
+    if shutter > 0. {
+        create_animation_2(2.)
+            .with_shutter(shutter)
+            .apply(0.25, &mut world, &mut camera)
+            .unwrap();
     }
 
-    let mut png_canvas = PngCanvas::new(resolution, resolution);
-    camera.render_to(&world, &mut png_canvas);
-    png_canvas.write_to_file("output.png").unwrap();
+    // the file format is picked based on the extension, eg `--output render.exr`,
+    // or `--output -` to write to stdout (using `--format` to pick the format, png by default)
+    let filename = arg_value(&args, "--output").unwrap_or_else(|| "output.png".to_string());
+    let format =
+        arg_value(&args, "--format").or_else(|| filename.rsplit('.').next().map(|e| e.to_string()));
+    let mut w: Box<dyn Write> = if filename == "-" {
+        Box::new(io::stdout())
+    } else {
+        Box::new(io::BufWriter::new(
+            fs::File::create(&filename).expect("couldn't create output file"),
+        ))
+    };
+
+    let buffer = output.render(&camera, &world);
+    output.write(buffer, format.as_deref(), &mut w).unwrap();
+    w.flush().unwrap();
+}
+
+// everything that happens to a scene after it's been set up
+struct Output {
+    display: DisplayTransform,
+    png_bit_depth: PngBitDepth,
+    alpha: bool,
+    post: Option<PostProcess>,
+}
+
+impl Output {
+    fn render(&self, camera: &Camera, world: &World) -> Framebuffer {
+        let buffer = render(camera, world, Framebuffer::new(camera.hsize, camera.vsize));
+        match &self.post {
+            Some(post) => {
+                let guides = if post.needs_guides() {
+                    Some(Guides::render(camera, world))
+                } else {
+                    None
+                };
+                post.apply(&buffer, guides.as_ref())
+            }
+            None => buffer,
+        }
+    }
+
+    fn write<W: Write>(
+        &self,
+        buffer: Framebuffer,
+        format: Option<&str>,
+        w: &mut W,
+    ) -> Result<(), CanvasError> {
+        match format {
+            Some("ppm") => PpmCanvas::from(buffer)
+                .with_display_transform(self.display)
+                .write_to(w),
+            Some("hdr") => HdrCanvas::from(buffer).write_to(w),
+            Some("pfm") => PfmCanvas::from(buffer).write_to(w),
+            Some("exr") => ExrCanvas::from(buffer).write_to(w),
+            _ => PngCanvas::from(buffer)
+                .with_display_transform(self.display)
+                .with_bit_depth(self.png_bit_depth)
+                .with_alpha(self.alpha)
+                .write_to(w),
+        }
+    }
+}
+
+fn render<T: Canvas>(camera: &Camera, world: &World, mut canvas: T) -> T {
+    camera.render_to(world, &mut canvas);
+    canvas
 }
@@ -0,0 +1,611 @@
+use crate::*;
+
+// how to get from one keyframe to the next
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interpolation {
+    // a straight line, so movement changes direction suddenly at each keyframe
+    Linear,
+    // a Catmull-Rom spline through the neighbouring keyframes, so movement
+    // flows smoothly through each keyframe
+    Smooth,
+}
+
+// values which can be animated
+pub trait Interpolate: Copy {
+    // the weighted sum of some values, where the weights add up to 1
+    fn blend(values: &[(Self, Float)]) -> Self;
+}
+
+impl Interpolate for Float {
+    fn blend(values: &[(Float, Float)]) -> Float {
+        values.iter().map(|(v, w)| v * w).sum()
+    }
+}
+
+impl Interpolate for Vector3 {
+    fn blend(values: &[(Vector3, Float)]) -> Vector3 {
+        values
+            .iter()
+            .fold(vec(0., 0., 0.), |acc, (v, w)| acc + v * *w)
+    }
+}
+
+impl Interpolate for Point3 {
+    fn blend(values: &[(Point3, Float)]) -> Point3 {
+        // points can't be added together, but since the weights add up to 1
+        // we can blend their offsets from the origin instead
+        let offsets = values
+            .iter()
+            .map(|(p, w)| (*p - Point3::origin(), *w))
+            .collect::<Vec<_>>();
+        Point3::origin() + Vector3::blend(&offsets)
+    }
+}
+
+impl Interpolate for Color {
+    fn blend(values: &[(Color, Float)]) -> Color {
+        values.iter().fold(black(), |acc, (v, w)| acc + v * *w)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keyframe<T> {
+    // in seconds
+    pub time: Float,
+    pub value: T,
+    // how to get to this keyframe from the one before it
+    pub interpolation: Interpolation,
+}
+
+// a value which changes over time, defined by a list of keyframes
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track<T> {
+    keyframes: Vec<Keyframe<T>>,
+}
+
+impl<T: Interpolate> Track<T> {
+    // a track which starts out with `value` at time 0
+    pub fn new(value: T) -> Track<T> {
+        Track {
+            keyframes: vec![Keyframe {
+                time: 0.,
+                value,
+                // unused, since there's nothing before the first keyframe
+                interpolation: Interpolation::Linear,
+            }],
+        }
+    }
+
+    // adds a keyframe, keeping them sorted by time
+    pub fn key(mut self, time: Float, value: T, interpolation: Interpolation) -> Track<T> {
+        let keyframe = Keyframe {
+            time,
+            value,
+            interpolation,
+        };
+        let i = self.keyframes.iter().position(|k| k.time > time);
+        self.keyframes
+            .insert(i.unwrap_or(self.keyframes.len()), keyframe);
+        self
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe<T>] {
+        &self.keyframes
+    }
+
+    // the value at `time`, which holds still before the first and after the last keyframes
+    pub fn sample(&self, time: Float) -> T {
+        let keys = &self.keyframes;
+        let next = match keys.iter().position(|k| k.time > time) {
+            Some(0) => return keys[0].value,
+            Some(i) => i,
+            None => return keys[keys.len() - 1].value,
+        };
+        let (k1, k2) = (&keys[next - 1], &keys[next]);
+        let t = (time - k1.time) / (k2.time - k1.time);
+
+        match k2.interpolation {
+            Interpolation::Linear => T::blend(&[(k1.value, 1. - t), (k2.value, t)]),
+            Interpolation::Smooth => {
+                // the first and last keyframes are repeated to give the spline something to aim for
+                let k0 = &keys[next.saturating_sub(2)];
+                let k3 = &keys[(next + 1).min(keys.len() - 1)];
+                let (t2, t3) = (t * t, t * t * t);
+                T::blend(&[
+                    (k0.value, 0.5 * (-t + 2. * t2 - t3)),
+                    (k1.value, 0.5 * (2. - 5. * t2 + 3. * t3)),
+                    (k2.value, 0.5 * (t + 4. * t2 - 3. * t3)),
+                    (k3.value, 0.5 * (-t2 + t3)),
+                ])
+            }
+        }
+    }
+}
+
+// an object's placement in the world, split up into parts which can be animated separately
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransformKey {
+    pub translation: Vector3,
+    // angles in radians around the x, y and z axes, applied in that order
+    pub rotation: Vector3,
+    pub scale: Vector3,
+}
+
+impl TransformKey {
+    pub fn new(translation: Vector3, rotation: Vector3, scale: Vector3) -> TransformKey {
+        TransformKey {
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    pub fn to_matrix(&self) -> Matrix4 {
+        let (t, r, s) = (self.translation, self.rotation, self.scale);
+        translation(t.x, t.y, t.z)
+            * rotation_z(r.z)
+            * rotation_y(r.y)
+            * rotation_x(r.x)
+            * scaling(s.x, s.y, s.z)
+    }
+}
+
+impl Default for TransformKey {
+    fn default() -> TransformKey {
+        TransformKey::new(vec(0., 0., 0.), vec(0., 0., 0.), vec(1., 1., 1.))
+    }
+}
+
+impl Interpolate for TransformKey {
+    fn blend(values: &[(TransformKey, Float)]) -> TransformKey {
+        TransformKey {
+            translation: blend_part(values, |k| k.translation),
+            rotation: blend_part(values, |k| k.rotation),
+            scale: blend_part(values, |k| k.scale),
+        }
+    }
+}
+
+// the arguments to `view_transform`, which are much easier to animate than the matrix itself
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ViewKey {
+    pub from: Point3,
+    pub to: Point3,
+    pub up: Vector3,
+}
+
+impl ViewKey {
+    pub fn new(from: Point3, to: Point3, up: Vector3) -> ViewKey {
+        ViewKey { from, to, up }
+    }
+
+    pub fn to_matrix(&self) -> Matrix4 {
+        view_transform(self.from, self.to, self.up)
+    }
+}
+
+impl Interpolate for ViewKey {
+    fn blend(values: &[(ViewKey, Float)]) -> ViewKey {
+        ViewKey {
+            from: blend_part(values, |k| k.from),
+            to: blend_part(values, |k| k.to),
+            up: blend_part(values, |k| k.up),
+        }
+    }
+}
+
+// blends one field of a compound value, eg the `from` point of some `ViewKey`s
+fn blend_part<K, T: Interpolate>(values: &[(K, Float)], field: fn(&K) -> T) -> T {
+    T::blend(
+        &values
+            .iter()
+            .map(|(v, w)| (field(v), *w))
+            .collect::<Vec<_>>(),
+    )
+}
+
+// tracks for the parts of a scene which move over time
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animation {
+    pub frames_per_second: Float,
+    // how long (in seconds) the camera's shutter stays open for each frame. Anything
+    // which moves during that time gets motion blurred, as long as the camera takes
+    // more than one sample per pixel.
+    pub shutter: Float,
+    pub camera: Option<Track<ViewKey>>,
+    // indexes into `World::objects`
+    pub objects: Vec<(usize, Track<TransformKey>)>,
+    // indexes into `World::lights`
+    pub lights: Vec<(usize, Track<Point3>)>,
+}
+
+impl Animation {
+    pub fn new(frames_per_second: Float) -> Animation {
+        Animation {
+            frames_per_second,
+            shutter: 0.,
+            camera: None,
+            objects: vec![],
+            lights: vec![],
+        }
+    }
+
+    pub fn with_shutter(mut self, shutter: Float) -> Animation {
+        self.shutter = shutter;
+        self
+    }
+
+    pub fn with_camera(mut self, track: Track<ViewKey>) -> Animation {
+        self.camera = Some(track);
+        self
+    }
+
+    pub fn with_object(mut self, index: usize, track: Track<TransformKey>) -> Animation {
+        self.objects.push((index, track));
+        self
+    }
+
+    pub fn with_light(mut self, index: usize, track: Track<Point3>) -> Animation {
+        self.lights.push((index, track));
+        self
+    }
+
+    pub fn time_of_frame(&self, frame: usize) -> Float {
+        frame as Float / self.frames_per_second
+    }
+
+    // moves everything to where it should be when the shutter opens at `time`,
+    // and sets up the camera and objects to move until it closes again. Tracks for objects
+    // or lights the world doesn't have give an error before anything is changed; keyframes
+    // which can't be used (eg a scale of 0) give one too, but leave the scene partly updated.
+    pub fn apply(
+        &self,
+        time: Float,
+        world: &mut World,
+        camera: &mut Camera,
+    ) -> Result<(), RtcError> {
+        let blurred = self.shutter > 0.;
+        let end_time = time + self.shutter;
+
+        let objects = self
+            .objects
+            .iter()
+            .map(|(index, _)| ("objects", *index, world.objects.len()));
+        let lights = self
+            .lights
+            .iter()
+            .map(|(index, _)| ("lights", *index, world.lights.len()));
+        if let Some((list, index, _)) = objects.chain(lights).find(|(_, index, len)| index >= len) {
+            return Err(RtcError::missing_object(&format!("{}[{}]", list, index)));
+        }
+
+        if let Some(track) = &self.camera {
+            camera.try_set_view_transform(track.sample(time).to_matrix())?;
+            camera.try_set_end_view_transform(if blurred {
+                Some(track.sample(end_time).to_matrix())
+            } else {
+                None
+            })?;
+        }
+        for (index, track) in &self.objects {
+            let name = format!("objects[{}]", index);
+            let object = world
+                .objects
+                .get_mut(*index)
+                .ok_or_else(|| RtcError::missing_object(&name))?;
+            let start = track.sample(time).to_matrix();
+            object
+                .try_set_transform(start)
+                .map_err(|e| e.in_object(&name))?;
+            object.set_motion(if blurred {
+                let end = track.sample(end_time).to_matrix();
+                Some(Motion::try_new(start, end).map_err(|e| e.in_object(&name))?)
+            } else {
+                None
+            });
+        }
+        for (index, track) in &self.lights {
+            let light = world
+                .lights
+                .get_mut(*index)
+                .ok_or_else(|| RtcError::missing_object(&format!("lights[{}]", index)))?;
+            *light = PointLight::new(light.intensity(), track.sample(time));
+        }
+        Ok(())
+    }
+
+    // sets up each frame in turn and passes it to `render_frame`, eg to render it to a file
+    pub fn render_frames<E, F>(
+        &self,
+        frames: std::ops::Range<usize>,
+        world: &mut World,
+        camera: &mut Camera,
+        mut render_frame: F,
+    ) -> Result<(), E>
+    where
+        F: FnMut(usize, &World, &Camera) -> Result<(), E>,
+        E: From<RtcError>,
+    {
+        for frame in frames {
+            self.apply(self.time_of_frame(frame), world, camera)?;
+            render_frame(frame, world, camera)?;
+        }
+        Ok(())
+    }
+}
+
+// replaces the (last) run of `#`s in `pattern` with the zero-padded frame number,
+// eg `frame_###.png` -> `frame_007.png`. Without any `#`s the number goes before the extension.
+pub fn frame_filename(pattern: &str, frame: usize) -> String {
+    match pattern.rfind('#') {
+        Some(end) => {
+            let start = pattern[..end]
+                .char_indices()
+                .rev()
+                .find(|&(_, c)| c != '#')
+                .map(|(i, c)| i + c.len_utf8())
+                .unwrap_or(0);
+            let width = end + 1 - start;
+            format!(
+                "{}{:0width$}{}",
+                &pattern[..start],
+                frame,
+                &pattern[end + 1..],
+                width = width
+            )
+        }
+        None => match pattern.rfind('.') {
+            Some(dot) => format!("{}_{:04}{}", &pattern[..dot], frame, &pattern[dot..]),
+            None => format!("{}_{:04}", pattern, frame),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate approx;
+    use self::approx::assert_relative_eq;
+    use super::*;
+    use crate::consts::PI;
+
+    #[test]
+    fn linear_tracks_interpolate_between_keyframes() {
+        let track =
+            Track::new(0.)
+                .key(2., 10., Interpolation::Linear)
+                .key(1., 4., Interpolation::Linear);
+
+        assert_eq!(0., track.sample(0.));
+        assert_eq!(2., track.sample(0.5));
+        assert_eq!(4., track.sample(1.));
+        assert_eq!(7., track.sample(1.5));
+        assert_eq!(10., track.sample(2.));
+    }
+
+    #[test]
+    fn tracks_hold_still_outside_their_keyframes() {
+        let track = Track::new(point(1., 2., 3.)).key(1., point(2., 2., 2.), Interpolation::Smooth);
+        assert_eq!(point(1., 2., 3.), track.sample(-1.));
+        assert_eq!(point(2., 2., 2.), track.sample(5.));
+    }
+
+    #[test]
+    fn smooth_tracks_pass_through_keyframes() {
+        let track = Track::new(vec(0., 0., 0.))
+            .key(1., vec(1., 2., 0.), Interpolation::Smooth)
+            .key(2., vec(3., 0., 1.), Interpolation::Smooth)
+            .key(3., vec(0., 0., 0.), Interpolation::Smooth);
+
+        for k in track.keyframes() {
+            assert_tuple_eq!(k.value, track.sample(k.time), epsilon = 0.00001);
+        }
+    }
+
+    #[test]
+    fn smooth_tracks_dont_change_direction_suddenly() {
+        let smooth =
+            Track::new(0.)
+                .key(1., 1., Interpolation::Smooth)
+                .key(2., 0., Interpolation::Smooth);
+        let linear =
+            Track::new(0.)
+                .key(1., 1., Interpolation::Linear)
+                .key(2., 0., Interpolation::Linear);
+
+        // approaching the middle keyframe, the smooth track slows down to turn around
+        let slope = |track: &Track<Float>| (track.sample(1.) - track.sample(0.99)) / 0.01;
+        assert_eq!(1., slope(&linear).round());
+        assert!(slope(&smooth).abs() < 0.1);
+    }
+
+    #[test]
+    fn transform_keys_build_matrices_and_interpolate() {
+        let a = TransformKey::default();
+        let b = TransformKey::new(vec(2., 0., 0.), vec(0., PI, 0.), vec(3., 3., 3.));
+        assert_eq!(Matrix4::identity(), a.to_matrix());
+
+        let half = Track::new(a).key(1., b, Interpolation::Linear).sample(0.5);
+        let expected = translation(1., 0., 0.) * rotation_y(PI / 2.) * scaling(2., 2., 2.);
+        assert_relative_eq!(expected, half.to_matrix(), epsilon = 0.0001);
+    }
+
+    #[test]
+    fn applying_an_animation_moves_the_scene() {
+        let mut w = World::default();
+        let mut c = Camera::from_size(11, 11, PI / 2.);
+
+        let animation = Animation::new(10.)
+            .with_camera(
+                Track::new(ViewKey::new(
+                    point(0., 0., -5.),
+                    point(0., 0., 0.),
+                    vec(0., 1., 0.),
+                ))
+                .key(
+                    1.,
+                    ViewKey::new(point(0., 0., -10.), point(0., 0., 0.), vec(0., 1., 0.)),
+                    Interpolation::Linear,
+                ),
+            )
+            .with_object(
+                1,
+                Track::new(TransformKey::default()).key(
+                    1.,
+                    TransformKey::new(vec(0., 4., 0.), vec(0., 0., 0.), vec(1., 1., 1.)),
+                    Interpolation::Linear,
+                ),
+            )
+            .with_light(
+                0,
+                Track::new(point(0., 0., 0.)).key(1., point(0., 10., 0.), Interpolation::Linear),
+            );
+
+        let time = animation.time_of_frame(5);
+        assert_eq!(0.5, time);
+        animation.apply(time, &mut w, &mut c).unwrap();
+
+        assert_eq!(
+            view_transform(point(0., 0., -7.5), point(0., 0., 0.), vec(0., 1., 0.)),
+            c.view_transform()
+        );
+        assert_relative_eq!(
+            translation(0., -2., 0.),
+            w.objects[1].world_to_object(),
+            epsilon = 0.0001
+        );
+        assert_eq!(point(0., 5., 0.), w.lights[0].position());
+        assert_eq!(white(), w.lights[0].intensity());
+    }
+
+    #[test]
+    fn animations_with_a_shutter_set_up_motion_blur() {
+        let mut w = World::default();
+        let mut c = Camera::from_size(11, 11, PI / 2.);
+        let moving = TransformKey::new(vec(4., 0., 0.), vec(0., 0., 0.), vec(1., 1., 1.));
+        let animation = Animation::new(10.)
+            .with_shutter(0.5)
+            .with_object(
+                0,
+                Track::new(TransformKey::default()).key(1., moving, Interpolation::Linear),
+            )
+            .with_camera(
+                Track::new(ViewKey::new(
+                    point(0., 0., -5.),
+                    point(0., 0., 0.),
+                    vec(0., 1., 0.),
+                ))
+                .key(
+                    1.,
+                    ViewKey::new(point(0., 0., -10.), point(0., 0., 0.), vec(0., 1., 0.)),
+                    Interpolation::Linear,
+                ),
+            );
+
+        animation.apply(0.25, &mut w, &mut c).unwrap();
+
+        // the object moves from 1/4 of the way along its track to 3/4
+        let object = &w.objects[0];
+        assert_relative_eq!(
+            translation(-1., 0., 0.),
+            object.world_to_object_at_time(0.),
+            epsilon = 0.0001
+        );
+        assert_relative_eq!(
+            translation(-3., 0., 0.),
+            object.world_to_object_at_time(1.),
+            epsilon = 0.0001
+        );
+        assert_eq!(
+            Some(view_transform(
+                point(0., 0., -8.75),
+                point(0., 0., 0.),
+                vec(0., 1., 0.)
+            )),
+            c.end_view_transform()
+        );
+    }
+
+    #[test]
+    fn keyframes_which_squash_objects_flat_give_errors() {
+        let mut w = World::default();
+        let mut c = Camera::from_size(11, 11, PI / 2.);
+        let flat = TransformKey::new(vec(0., 0., 0.), vec(0., 0., 0.), vec(1., 0., 1.));
+        let animation = Animation::new(10.).with_object(
+            1,
+            Track::new(TransformKey::default()).key(1., flat, Interpolation::Linear),
+        );
+
+        assert!(animation.apply(0.5, &mut w, &mut c).is_ok());
+        assert_eq!(
+            Err(RtcError::singular_transform("Sphere", scaling(1., 0., 1.)).in_object("objects[1]")),
+            animation.apply(1., &mut w, &mut c)
+        );
+
+        // the shutter closes on the flat keyframe, so the motion can't be used either
+        let blurred = animation.with_shutter(0.5);
+        let e = blurred.apply(0.5, &mut w, &mut c).unwrap_err();
+        assert!(
+            e.to_string().starts_with("Motion end 'objects[1]'"),
+            "{}",
+            e
+        );
+    }
+
+    #[test]
+    fn tracks_for_missing_objects_give_errors_without_changing_anything() {
+        let mut w = World::default();
+        let mut c = Camera::from_size(11, 11, PI / 2.);
+        let moved = TransformKey::new(vec(0., 4., 0.), vec(0., 0., 0.), vec(1., 1., 1.));
+        let animation = Animation::new(10.)
+            .with_camera(Track::new(ViewKey::new(
+                point(0., 0., -5.),
+                point(0., 0., 0.),
+                vec(0., 1., 0.),
+            )))
+            .with_object(0, Track::new(moved))
+            .with_light(3, Track::new(point(0., 10., 0.)));
+
+        let e = animation.apply(0., &mut w, &mut c).unwrap_err();
+        assert_eq!(RtcError::missing_object("lights[3]"), e);
+        assert_eq!("lights[3] isn't in the world", e.to_string());
+        assert_eq!(Matrix4::identity(), c.view_transform());
+        assert_eq!(
+            World::default().objects[0].transform(),
+            w.objects[0].transform()
+        );
+
+        let animation = Animation::new(10.).with_object(2, Track::new(moved));
+        let e = animation.apply(0., &mut w, &mut c).unwrap_err();
+        assert_eq!(RtcError::missing_object("objects[2]"), e);
+    }
+
+    #[test]
+    fn rendering_frames_visits_each_frame_in_order() {
+        let mut w = World::default();
+        let mut c = Camera::from_size(11, 11, PI / 2.);
+        let animation = Animation::new(2.).with_light(
+            0,
+            Track::new(point(0., 0., 0.)).key(10., point(10., 0., 0.), Interpolation::Linear),
+        );
+
+        let mut seen = vec![];
+        animation
+            .render_frames(2..5, &mut w, &mut c, |frame, world, _| {
+                seen.push((frame, world.lights[0].position().x));
+                Ok::<(), RtcError>(())
+            })
+            .unwrap();
+        assert_eq!(vec![(2, 1.), (3, 1.5), (4, 2.)], seen);
+    }
+
+    #[test]
+    fn frame_filenames_are_numbered() {
+        assert_eq!("frame_007.png", frame_filename("frame_###.png", 7));
+        assert_eq!("frame_1234.png", frame_filename("frame_#.png", 1234));
+        assert_eq!("out/##/f0012.png", frame_filename("out/##/f####.png", 12));
+        assert_eq!("render_0003.png", frame_filename("render.png", 3));
+        assert_eq!("render_0003", frame_filename("render", 3));
+        assert_eq!("é007.png", frame_filename("é###.png", 7));
+        assert_eq!("фильм_12.png", frame_filename("фильм_##.png", 12));
+    }
+}
@@ -1,15 +1,17 @@
 extern crate float_cmp;
-pub use self::float_cmp::approx_eq;
+pub use self::float_cmp::{approx_eq, ApproxEq};
 
 // code crudely copied from https://doc.rust-lang.org/src/core/macros.rs.html#78-111
 // ... do we need a macro-generating macro here?
 
+// works for any of Point3, Vector3 or Normal3
 #[cfg(test)]
 macro_rules! assert_tuple_eq {
         ($left:expr, $right:expr $(, $set:ident = $val:expr)*) => {{
             match (&$left, &$right) {
                 (left_val, right_val) => {
-                    if !approx_eq!(Tuple, *left_val, *right_val $(, $set = $val)*) {
+                    let margin = $crate::FloatMargin::default()$(.$set($val))*;
+                    if !$crate::ApproxEq::approx_eq(*left_val, *right_val, margin) {
                         panic!(
                             r#"assertion failed: `(left approxEquals right)`
    left: `{:?}`
@@ -0,0 +1,345 @@
+use crate::consts::PI;
+use crate::*;
+
+// what rays see when they don't hit anything. It only depends on the ray's
+// direction, as if it was infinitely far away.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Background {
+    Solid(Color),
+    // blends from `bottom` (looking straight down) to `top` (looking straight up)
+    Gradient { bottom: Color, top: Color },
+    Sky(PhysicalSky),
+    Image(EnvironmentMap),
+}
+
+impl Background {
+    pub fn color_in(&self, direction: Vector3) -> Color {
+        let direction = direction.normalize();
+
+        match self {
+            Background::Solid(c) => *c,
+            Background::Gradient { bottom, top } => {
+                let t = 0.5 * (direction.y + 1.);
+                *bottom * (1. - t) + *top * t
+            }
+            Background::Sky(s) => s.color_in(direction),
+            Background::Image(m) => m.color_in(direction),
+        }
+    }
+}
+
+impl Default for Background {
+    fn default() -> Background {
+        Background::Solid(black())
+    }
+}
+
+// the Preetham et al. daylight model, see "A Practical Analytic Model for Daylight" (1999).
+// The sky's brightness and colour come from the sun's position and the turbidity
+// (how hazy the air is: around 2 for a clear day, up to around 10 for a hazy one).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhysicalSky {
+    sun_direction: Vector3,
+    turbidity: Float,
+    // the model's luminance is in kcd/m², so this scales it to be comparable with the scene's lights
+    pub intensity: Float,
+}
+
+impl PhysicalSky {
+    pub fn new(sun_direction: Vector3, turbidity: Float) -> PhysicalSky {
+        PhysicalSky {
+            sun_direction: sun_direction.normalize(),
+            turbidity,
+            intensity: 0.1,
+        }
+    }
+
+    pub fn with_intensity(mut self, intensity: Float) -> PhysicalSky {
+        self.intensity = intensity;
+        self
+    }
+
+    pub fn sun_direction(&self) -> Vector3 {
+        self.sun_direction
+    }
+
+    pub fn turbidity(&self) -> Float {
+        self.turbidity
+    }
+
+    fn color_in(&self, direction: Vector3) -> Color {
+        let t = self.turbidity;
+        // the model isn't defined below the horizon, so carry on with the horizon's colour
+        let direction = if direction.y < 0. {
+            let horizontal = vec(direction.x, 0., direction.z);
+            // looking straight down, every part of the horizon is as near as any other
+            if horizontal.magnitude() > Float::EPSILON {
+                horizontal.normalize()
+            } else {
+                vec(1., 0., 0.)
+            }
+        } else {
+            direction
+        };
+        let cos_theta = direction.y.max(0.001);
+        let cos_gamma = direction.dot(self.sun_direction).clamp(-1., 1.);
+        let gamma = cos_gamma.acos();
+        // the sun's angle from the zenith
+        let theta_sun = self.sun_direction.y.clamp(-1., 1.).acos();
+
+        // each of Y, x and y is the zenith's value, scaled by how much brighter or darker
+        // this direction is than the zenith according to the Perez sky distribution
+        let relative = |coefficients: [Float; 5]| {
+            perez(coefficients, cos_theta, gamma, cos_gamma)
+                / perez(coefficients, 1., theta_sun, theta_sun.cos())
+        };
+
+        let chi = (4. / 9. - t / 120.) * (PI - 2. * theta_sun);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let zenith_x = zenith_chromaticity(
+            t,
+            theta_sun,
+            [
+                [0.00166, -0.00375, 0.00209, 0.],
+                [-0.02903, 0.06377, -0.03202, 0.00394],
+                [0.11693, -0.21196, 0.06052, 0.25886],
+            ],
+        );
+        let zenith_y = zenith_chromaticity(
+            t,
+            theta_sun,
+            [
+                [0.00275, -0.00610, 0.00317, 0.],
+                [-0.04214, 0.08970, -0.04153, 0.00516],
+                [0.15346, -0.26756, 0.06670, 0.26688],
+            ],
+        );
+
+        let luminance = zenith_luminance
+            * relative([
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ]);
+        let x = zenith_x
+            * relative([
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ]);
+        let y = zenith_y
+            * relative([
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ]);
+
+        xyy_to_rgb(x, y, luminance.max(0.) * self.intensity)
+    }
+}
+
+// the Perez et al. all-weather sky luminance distribution, for a direction `theta`
+// from the zenith and `gamma` from the sun
+fn perez(coefficients: [Float; 5], cos_theta: Float, gamma: Float, cos_gamma: Float) -> Float {
+    let [a, b, c, d, e] = coefficients;
+    (1. + a * (b / cos_theta).exp()) * (1. + c * (d * gamma).exp() + e * cos_gamma * cos_gamma)
+}
+
+// a polynomial in the turbidity and the sun's angle, from the appendix of the Preetham paper
+fn zenith_chromaticity(turbidity: Float, theta_sun: Float, coefficients: [[Float; 4]; 3]) -> Float {
+    let angles = [theta_sun.powi(3), theta_sun.powi(2), theta_sun, 1.];
+    let row = |r: [Float; 4]| r.iter().zip(&angles).map(|(c, a)| c * a).sum::<Float>();
+    turbidity * turbidity * row(coefficients[0])
+        + turbidity * row(coefficients[1])
+        + row(coefficients[2])
+}
+
+// CIE xyY to linear sRGB, see https://en.wikipedia.org/wiki/SRGB#The_reverse_transformation
+fn xyy_to_rgb(x: Float, y: Float, luminance: Float) -> Color {
+    let big_x = x * luminance / y;
+    let big_y = luminance;
+    let big_z = (1. - x - y) * luminance / y;
+    Color::new(
+        3.2406 * big_x - 1.5372 * big_y - 0.4986 * big_z,
+        -0.9689 * big_x + 1.8758 * big_y + 0.0415 * big_z,
+        0.0557 * big_x - 0.2040 * big_y + 1.0570 * big_z,
+    )
+}
+
+// an image covering every direction, laid out with longitude across and latitude down
+// (the equirectangular projection, like the `SphereMap` pattern uses)
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvironmentMap {
+    image: Framebuffer,
+    // transformation from world space to the map's space, eg to turn the map around
+    world_to_map: Matrix4,
+}
+
+impl EnvironmentMap {
+    // `image` should hold linear colors, like a rendered Framebuffer does
+    pub fn new(image: Framebuffer, transform: impl Into<Matrix4>) -> EnvironmentMap {
+        EnvironmentMap::try_new(image, transform).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_new(
+        image: Framebuffer,
+        transform: impl Into<Matrix4>,
+    ) -> Result<EnvironmentMap, RtcError> {
+        let world_to_map = CachedTransform::for_object(transform, "EnvironmentMap")?.inverse();
+
+        Ok(EnvironmentMap {
+            image,
+            world_to_map,
+        })
+    }
+
+    // for sRGB encoded images, like most PNG files
+    pub fn from_srgb(image: &Framebuffer, transform: impl Into<Matrix4>) -> EnvironmentMap {
+        let mut linear = image.clone();
+        for (x, y, c) in image.pixels() {
+            let c = Color::new(
+                srgb_to_linear(c.r),
+                srgb_to_linear(c.g),
+                srgb_to_linear(c.b),
+            );
+            linear.write_pixel(&c, x, y);
+        }
+        EnvironmentMap::new(linear, transform)
+    }
+
+    fn color_in(&self, direction: Vector3) -> Color {
+        let d = (self.world_to_map * direction).normalize();
+        let (u, v) = spherical_uv(point(d.x, d.y, d.z));
+        self.sample(u, v)
+    }
+
+    // bilinear filtering between the 4 nearest pixels. The image wraps around horizontally,
+    // and is clamped vertically since the top and bottom rows are the poles.
+    fn sample(&self, u: Float, v: Float) -> Color {
+        let width = self.image.width();
+        let height = self.image.height();
+        if width == 0 || height == 0 {
+            return black();
+        }
+
+        // measured from pixel centres
+        let x = u * width as Float - 0.5;
+        let y = (v * height as Float - 0.5).clamp(0., (height - 1) as Float);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+
+        let column = |x: Float| (x as isize).rem_euclid(width as isize) as usize;
+        let row = |y: Float| (y as usize).min(height - 1);
+        let pixel = |x: Float, y: Float| self.image.pixel_at(column(x), row(y));
+
+        let top = pixel(x0, y0) * (1. - fx) + pixel(x0 + 1., y0) * fx;
+        let bottom = pixel(x0, y0 + 1.) * (1. - fx) + pixel(x0 + 1., y0 + 1.) * fx;
+        top * (1. - fy) + bottom * fy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solid_backgrounds_are_the_same_in_every_direction() {
+        let b = Background::Solid(Color::new(0.1, 0.2, 0.3));
+        assert_eq!(Color::new(0.1, 0.2, 0.3), b.color_in(vec(0., 1., 0.)));
+        assert_eq!(Color::new(0.1, 0.2, 0.3), b.color_in(vec(1., -1., 2.)));
+        assert_eq!(black(), Background::default().color_in(vec(0., 0., 1.)));
+    }
+
+    #[test]
+    fn gradients_blend_from_bottom_to_top() {
+        let b = Background::Gradient {
+            bottom: white(),
+            top: Color::new(0.2, 0.4, 1.),
+        };
+        assert_color_eq!(white(), b.color_in(vec(0., -1., 0.)), epsilon = 0.0001);
+        assert_color_eq!(
+            Color::new(0.2, 0.4, 1.),
+            b.color_in(vec(0., 3., 0.)),
+            epsilon = 0.0001
+        );
+        assert_color_eq!(
+            Color::new(0.6, 0.7, 1.),
+            b.color_in(vec(1., 0., 1.)),
+            epsilon = 0.0001
+        );
+    }
+
+    #[test]
+    fn physical_sky_is_blue_and_brightest_around_the_sun() {
+        let sky = PhysicalSky::new(vec(0., 1., 1.), 3.);
+        let b = Background::Sky(sky);
+
+        let zenith = b.color_in(vec(0., 1., 0.));
+        assert!(zenith.b > zenith.r);
+
+        let near_sun = b.color_in(vec(0., 1., 1.1));
+        let away_from_sun = b.color_in(vec(0., 1., -1.));
+        assert!(near_sun.g > away_from_sun.g);
+
+        // looking down gives the same colour as the horizon
+        assert_eq!(b.color_in(vec(1., 0., 0.)), b.color_in(vec(1., -1., 0.)));
+        // including straight down, where there isn't a horizontal direction to follow
+        let straight_down = b.color_in(vec(0., -1., 0.));
+        assert_eq!(b.color_in(vec(1., 0., 0.)), straight_down);
+    }
+
+    #[test]
+    fn hazy_skies_are_less_blue() {
+        let blueness = |turbidity| {
+            let c = Background::Sky(PhysicalSky::new(vec(0., 1., 1.), turbidity))
+                .color_in(vec(0., 1., -1.));
+            c.b / c.r
+        };
+        assert!(blueness(2.) > blueness(8.));
+    }
+
+    #[test]
+    fn environment_maps_are_looked_up_by_direction() {
+        // left half red, right half white, and a blue bottom row
+        let mut image = Framebuffer::new(4, 3);
+        for y in 0..3 {
+            for x in 0..4 {
+                let c = match (x, y) {
+                    (_, 2) => Color::new(0., 0., 1.),
+                    (0, _) | (1, _) => red(),
+                    _ => white(),
+                };
+                image.write_pixel(&c, x, y);
+            }
+        }
+        let b = Background::Image(EnvironmentMap::new(image.clone(), Matrix4::identity()));
+
+        // +x is in the middle of the image, between the two halves
+        assert_color_eq!(
+            Color::new(1., 0.5, 0.5),
+            b.color_in(vec(1., 0., 0.)),
+            epsilon = 0.0001
+        );
+        assert_color_eq!(red(), b.color_in(vec(0., 0., -1.)), epsilon = 0.0001);
+        assert_color_eq!(white(), b.color_in(vec(0., 0., 1.)), epsilon = 0.0001);
+        assert_color_eq!(
+            Color::new(0., 0., 1.),
+            b.color_in(vec(0., -1., 0.)),
+            epsilon = 0.0001
+        );
+
+        // turning the map around swaps the two halves
+        let turned = EnvironmentMap::new(image, rotation_y(PI));
+        assert_color_eq!(
+            white(),
+            Background::Image(turned).color_in(vec(0., 0., -1.)),
+            epsilon = 0.0001
+        );
+    }
+}
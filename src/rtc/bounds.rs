@@ -0,0 +1,97 @@
+use crate::*;
+
+// an axis aligned box around a shape in its object space, which is cheap to test
+// rays against before trying the shape itself
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min: Point3,
+    pub max: Point3,
+}
+
+impl BoundingBox {
+    pub fn new(min: Point3, max: Point3) -> BoundingBox {
+        BoundingBox { min, max }
+    }
+
+    // for shapes which go on forever, like planes. Every ray hits these.
+    pub fn infinite() -> BoundingBox {
+        BoundingBox::new(
+            point(-Float::INFINITY, -Float::INFINITY, -Float::INFINITY),
+            point(Float::INFINITY, Float::INFINITY, Float::INFINITY),
+        )
+    }
+
+    // the "slab" test: the ray is inside the box where it's between the min and max planes
+    // on all three axes at once. Only hits in front of the ray's origin count.
+    pub fn intersects(&self, ray: Ray) -> bool {
+        let axis = |min: Float, max: Float, origin: Float, direction: Float| {
+            let t0 = (min - origin) / direction;
+            let t1 = (max - origin) / direction;
+            (t0.min(t1), t0.max(t1))
+        };
+
+        let (x0, x1) = axis(self.min.x, self.max.x, ray.origin.x, ray.direction.x);
+        let (y0, y1) = axis(self.min.y, self.max.y, ray.origin.y, ray.direction.y);
+        let (z0, z1) = axis(self.min.z, self.max.z, ray.origin.z, ray.direction.z);
+
+        let enter = x0.max(y0).max(z0);
+        let exit = x1.min(y1).min(z1);
+        enter <= exit && exit >= 0.
+    }
+
+    // the same test for four rays at once
+    pub fn intersects_packet(&self, packet: &RayPacket) -> Mask4 {
+        let axis = |min: Float, max: Float, origin: Float4, direction: Float4| {
+            let t0 = (Float4::splat(min) - origin) / direction;
+            let t1 = (Float4::splat(max) - origin) / direction;
+            (t0.min(t1), t0.max(t1))
+        };
+
+        let (o, d) = (&packet.origin, &packet.direction);
+        let (x0, x1) = axis(self.min.x, self.max.x, o.x, d.x);
+        let (y0, y1) = axis(self.min.y, self.max.y, o.y, d.y);
+        let (z0, z1) = axis(self.min.z, self.max.z, o.z, d.z);
+
+        let enter = x0.max(y0).max(z0);
+        let exit = x1.min(y1).min(z1);
+        enter.le(exit) & exit.ge(Float4::splat(0.))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rays_hitting_and_missing_a_box() {
+        let b = BoundingBox::new(point(-1., -1., -1.), point(1., 2., 1.));
+        let rays = [
+            // straight through the middle
+            Ray::new(point(0., 0., -5.), vec(0., 0., 1.)),
+            // diagonally into the tall end
+            Ray::new(point(-5., 6., 0.), vec(1., -1., 0.)),
+            // passing by above
+            Ray::new(point(0., 3., -5.), vec(0., 0., 1.)),
+            // the box is behind the ray
+            Ray::new(point(0., 0., 5.), vec(0., 0., 1.)),
+        ];
+
+        let expected = [true, true, false, false];
+        let packet = b.intersects_packet(&RayPacket::new(rays));
+        for i in 0..4 {
+            assert_eq!(expected[i], b.intersects(rays[i]));
+            assert_eq!(expected[i], packet.lane(i));
+        }
+
+        // rays starting inside the box always hit it
+        assert!(b.intersects(Ray::new(point(0., 0., 0.), vec(1., 0., 0.))));
+    }
+
+    #[test]
+    fn every_ray_hits_an_infinite_box() {
+        let b = BoundingBox::infinite();
+        let r = Ray::new(point(0., 100., 0.), vec(0., 1., 0.));
+        assert!(b.intersects(r));
+        assert!(b.intersects_packet(&RayPacket::new([r; 4])).all());
+    }
+}
@@ -5,22 +5,44 @@ pub struct Camera {
     pub hsize: usize,
     pub vsize: usize,
     // field of view angle in radians
-    pub fov: f32,
-    pub view_transform: Matrix4,
-    pub half_width: f32,
-    pub half_height: f32,
-    pub pixel_size: f32,
+    pub fov: Float,
+    // world -> camera transformation, see `view_transform()`
+    view_transform: CachedTransform,
+    // for motion blur: the view transforms when the shutter opens and closes
+    motion: Option<TransformBlend>,
+    pub half_width: Float,
+    pub half_height: Float,
+    pub pixel_size: Float,
+    pub integrator: Integrator,
+    // when more than one sample is taken, each one is jittered randomly within the pixel
+    pub samples_per_pixel: usize,
+    // used to seed the random number generator for each pixel
+    pub seed: u64,
 }
 
 impl Camera {
-    pub fn new(hsize: usize, vsize: usize, fov: f32, view_transform: Matrix4) -> Camera {
+    pub fn new(
+        hsize: usize,
+        vsize: usize,
+        fov: Float,
+        view_transform: impl Into<Matrix4>,
+    ) -> Camera {
+        Camera::try_new(hsize, vsize, fov, view_transform).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_new(
+        hsize: usize,
+        vsize: usize,
+        fov: Float,
+        view_transform: impl Into<Matrix4>,
+    ) -> Result<Camera, RtcError> {
         // - for this camera we assume the "canvas" is one unit away.
         // - fov/2 gives us the angle between edge and center of the canvas.
         // - since tan(fov/2) gives us the ratio of the opposite/adjacent tri sides,
         //   and we define the adjacent to be one unit long, half_view becomes
         //   half of the canvas size
         let half_view = (fov / 2.).tan();
-        let aspect_ratio = hsize as f32 / vsize as f32;
+        let aspect_ratio = hsize as Float / vsize as Float;
 
         // let's say that fov describes a circle which our canvas fits into.
         let (half_width, half_height) = if aspect_ratio >= 1. {
@@ -34,69 +56,189 @@ impl Camera {
 
         // the pixel size is the full width of the canvas divided by the width in pixels
         // we assume square pixels so the height calculation would be equivalent
-        let pixel_size = (half_width * 2.) / hsize as f32;
+        let pixel_size = (half_width * 2.) / hsize as Float;
 
-        Camera {
+        Ok(Camera {
             hsize,
             vsize,
             fov,
-            view_transform,
+            view_transform: CachedTransform::for_object(view_transform, "Camera")?,
+            motion: None,
             half_width,
             half_height,
             pixel_size,
-        }
+            integrator: Integrator::default(),
+            samples_per_pixel: 1,
+            seed: 0,
+        })
     }
 
-    pub fn from_size(hsize: usize, vsize: usize, fov: f32) -> Camera {
+    pub fn from_size(hsize: usize, vsize: usize, fov: Float) -> Camera {
         Camera::new(hsize, vsize, fov, Matrix4::identity())
     }
 
+    pub fn view_transform(&self) -> Matrix4 {
+        self.view_transform.matrix()
+    }
+
+    pub fn set_view_transform(&mut self, view_transform: impl Into<Matrix4>) {
+        self.try_set_view_transform(view_transform)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_set_view_transform(
+        &mut self,
+        view_transform: impl Into<Matrix4>,
+    ) -> Result<(), RtcError> {
+        self.view_transform = CachedTransform::for_object(view_transform, "Camera")?;
+        self.motion = self
+            .motion
+            .map(|m| TransformBlend::new(self.view_transform, m.end()));
+        Ok(())
+    }
+
+    pub fn end_view_transform(&self) -> Option<Matrix4> {
+        self.motion.map(|m| m.end().matrix())
+    }
+
+    // makes the camera move from its view transform to `end` while the shutter is open,
+    // or stops it moving if `end` is None
+    pub fn try_set_end_view_transform(&mut self, end: Option<Matrix4>) -> Result<(), RtcError> {
+        self.motion = match end {
+            Some(end) => Some(TransformBlend::new(
+                self.view_transform,
+                CachedTransform::for_object(end, "Camera")?,
+            )),
+            None => None,
+        };
+        Ok(())
+    }
+
     pub fn ray_for_pixel(&self, x: usize, y: usize) -> Ray {
-        // the offset from the edge of the canvas to the pixel's *center*
-        let xoffset = (x as f32 + 0.5) * self.pixel_size;
-        let yoffset = (y as f32 + 0.5) * self.pixel_size;
+        // aim for the pixel's *center*
+        self.ray_for_canvas_point(x as Float + 0.5, y as Float + 0.5, 0.)
+    }
+
+    // a ray through an arbitrary point on the canvas, measured in pixels from the top-left
+    // corner, cast at `time` (see `Ray::time`)
+    pub fn ray_for_canvas_point(&self, x: Float, y: Float, time: Float) -> Ray {
+        // the offset from the edge of the canvas to the point
+        let xoffset = x * self.pixel_size;
+        let yoffset = y * self.pixel_size;
 
         // the default camera looks towards -z, so we subtract these values
         let pixel_world_x = self.half_width - xoffset;
         let pixel_world_y = self.half_height - yoffset;
 
-        let camera_to_world = self
-            .view_transform
-            .try_inverse()
-            .expect("Panic! Camera transform can't be inverted!");
+        // a moving camera's inverse depends on the ray's time, but otherwise it's only
+        // worked out once. A turning camera turns in an arc, like a turning shape.
+        let camera_to_world = match &self.motion {
+            Some(motion) => motion.inverse_at(time),
+            None => self.view_transform.inverse(),
+        };
 
         let pixel_pos = camera_to_world * point(pixel_world_x, pixel_world_y, -1.);
         let origin = camera_to_world * point(0., 0., 0.);
         let direction = (pixel_pos - origin).normalize();
 
-        Ray::new(origin, direction)
+        Ray::new(origin, direction).with_time(time)
     }
 
     pub fn render_to<T: Canvas>(&self, world: &World, canvas: &mut T) {
         for y in 0..self.vsize - 1 {
+            if self.samples_per_pixel <= 1 {
+                self.render_row_in_packets(world, canvas, y);
+                continue;
+            }
             for x in 0..self.hsize - 1 {
-                let ray = self.ray_for_pixel(x, y);
-                let color = color_at(world, ray);
+                let (color, alpha) = self.sample_pixel(world, x, y);
                 canvas.write_pixel(&color, x, y);
+                canvas.write_alpha(alpha, x, y);
+            }
+        }
+    }
+
+    // with one sample per pixel, neighbouring rays are close enough together to
+    // be worth tracing 4 at a time. This gives the same result as `sample_pixel`.
+    fn render_row_in_packets<T: Canvas>(&self, world: &World, canvas: &mut T, y: usize) {
+        let width = self.hsize - 1;
+        for x0 in (0..width).step_by(4) {
+            // the last packet in the row is padded out with copies of its last ray
+            let x = |i: usize| (x0 + i).min(width - 1);
+            let rays = [
+                self.ray_for_pixel(x(0), y),
+                self.ray_for_pixel(x(1), y),
+                self.ray_for_pixel(x(2), y),
+                self.ray_for_pixel(x(3), y),
+            ];
+            let hits = world.packet_hits(&RayPacket::new(rays));
+
+            for i in 0..4.min(width - x0) {
+                let mut rng = Rng::for_pixel(self.seed, x(i), y);
+                let color = self
+                    .integrator
+                    .color_for_hit(world, rays[i], hits[i], &mut rng);
+                let alpha = if hits[i].is_some() { 1. } else { 0. };
+                canvas.write_pixel(&color, x(i), y);
+                canvas.write_alpha(alpha, x(i), y);
             }
         }
     }
+
+    pub fn color_for_pixel(&self, world: &World, x: usize, y: usize) -> Color {
+        self.sample_pixel(world, x, y).0
+    }
+
+    // the color of a pixel, along with the fraction of its samples which hit something
+    fn sample_pixel(&self, world: &World, x: usize, y: usize) -> (Color, Float) {
+        let mut rng = Rng::for_pixel(self.seed, x, y);
+
+        if self.samples_per_pixel <= 1 {
+            let ray = self.ray_for_pixel(x, y);
+            return self.sample_ray(world, ray, &mut rng);
+        }
+
+        let mut total = black();
+        let mut total_coverage = 0.;
+        for _ in 0..self.samples_per_pixel {
+            // each sample is jittered in both space and time, which gives antialiasing
+            // and (when anything is moving) motion blur
+            let (sx, sy) = (x as Float + rng.next_float(), y as Float + rng.next_float());
+            let ray = self.ray_for_canvas_point(sx, sy, rng.next_float());
+            let (color, coverage) = self.sample_ray(world, ray, &mut rng);
+            total += color;
+            total_coverage += coverage;
+        }
+        let n = self.samples_per_pixel as Float;
+        (total * (1. / n), total_coverage / n)
+    }
+
+    // the color seen along a camera ray, and whether it hit anything. The ray is only
+    // traced once, with the hit shared between the integrator and the alpha channel.
+    fn sample_ray(&self, world: &World, ray: Ray, rng: &mut Rng) -> (Color, Float) {
+        let hit = world.closest_hit(ray, 0., Float::INFINITY);
+        let coverage = if hit.is_some() { 1. } else { 0. };
+        (
+            self.integrator.color_for_hit(world, ray, hit, rng),
+            coverage,
+        )
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::f32::consts::PI;
+    use crate::consts::PI;
 
     #[test]
     fn pixel_size_for_horizontal_canvas() {
         let c = Camera::from_size(200, 125, PI / 2.);
-        assert_eq!(0.01, c.pixel_size)
+        assert!(approx_eq!(Float, 0.01, c.pixel_size, epsilon = 0.00001))
     }
     #[test]
     fn pixel_size_for_vertical_canvas() {
         let c = Camera::from_size(125, 200, PI / 2.);
-        assert_eq!(0.01, c.pixel_size)
+        assert!(approx_eq!(Float, 0.01, c.pixel_size, epsilon = 0.00001))
     }
 
     #[test]
@@ -120,9 +262,165 @@ mod tests {
     #[test]
     fn ray_for_pixel_at_with_transformed_camera() {
         let mut c = Camera::from_size(201, 101, PI / 2.);
-        c.view_transform = rotation_y(PI / 4.) * translation(0., -2., 5.);
+        c.set_view_transform(rotation_y(PI / 4.) * translation(0., -2., 5.));
         let r = c.ray_for_pixel(100, 50);
-        let s22 = 2_f32.sqrt() / 2.;
+        let s22 = Float::sqrt(2.) / 2.;
         assert_ray_eq!(Ray::new(point(0., 2., -5.), vec(s22, 0., -s22)), r);
     }
+
+    #[test]
+    fn single_sample_uses_the_pixel_center() {
+        let w = World::default();
+        let c = Camera::new(
+            11,
+            11,
+            PI / 2.,
+            view_transform(point(0., 0., -5.), point(0., 0., 0.), vec(0., 1., 0.)),
+        );
+        assert_eq!(
+            color_at(&w, c.ray_for_pixel(5, 5)),
+            c.color_for_pixel(&w, 5, 5)
+        );
+    }
+
+    #[test]
+    fn multiple_samples_are_averaged_and_repeatable() {
+        let w = World::default();
+        let mut c = Camera::new(
+            11,
+            11,
+            PI / 2.,
+            view_transform(point(0., 0., -5.), point(0., 0., 0.), vec(0., 1., 0.)),
+        );
+        c.samples_per_pixel = 16;
+        c.integrator = Integrator::PathTracer(PathTracer::default());
+
+        let c1 = c.color_for_pixel(&w, 5, 5);
+        assert_eq!(c1, c.color_for_pixel(&w, 5, 5));
+
+        c.seed = 1;
+        assert_ne!(c1, c.color_for_pixel(&w, 5, 5));
+    }
+
+    #[test]
+    fn rendering_records_where_the_scene_was_hit() {
+        let w = World::default();
+        let c = Camera::new(
+            11,
+            11,
+            PI / 2.,
+            view_transform(point(0., 0., -5.), point(0., 0., 0.), vec(0., 1., 0.)),
+        );
+        let mut f = Framebuffer::new(11, 11);
+        c.render_to(&w, &mut f);
+
+        assert_eq!(1., f.alpha_at(5, 5));
+        assert_eq!(0., f.alpha_at(0, 0));
+    }
+
+    #[test]
+    fn rendering_in_packets_matches_rendering_each_pixel() {
+        let mut w = World::default();
+        w.objects.push(Box::new(Plane::t(translation(0., -1., 0.))));
+        let mut c = Camera::new(
+            10,
+            7,
+            PI / 2.,
+            view_transform(point(0., 0.5, -3.), point(0., 0., 0.), vec(0., 1., 0.)),
+        );
+        let integrators = vec![
+            Integrator::Whitted,
+            Integrator::WhittedWithOcclusion(AmbientOcclusion::new(4, 2.)),
+            Integrator::PathTracer(PathTracer::default()),
+            Integrator::Occlusion(AmbientOcclusion::new(4, 2.)),
+            Integrator::Aov(Aov::Depth { max_distance: 10. }),
+        ];
+        for integrator in integrators {
+            c.integrator = integrator;
+            let mut f = Framebuffer::new(10, 7);
+            c.render_to(&w, &mut f);
+
+            // 9 isn't a multiple of 4, so the last packet in each row is only partly used
+            for y in 0..6 {
+                for x in 0..9 {
+                    let (color, alpha) = c.sample_pixel(&w, x, y);
+                    assert_color_eq!(color, f.pixel_at(x, y), epsilon = 0.0001);
+                    assert_eq!(alpha, f.alpha_at(x, y));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn moving_camera_casts_rays_from_where_it_is_at_the_time() {
+        let mut c = Camera::from_size(11, 11, PI / 2.);
+        c.try_set_end_view_transform(Some(translation(0., -2., 0.)))
+            .unwrap();
+
+        assert_tuple_eq!(
+            point(0., 0., 0.),
+            c.ray_for_canvas_point(5.5, 5.5, 0.).origin
+        );
+        let r = c.ray_for_canvas_point(5.5, 5.5, 0.5);
+        assert_tuple_eq!(point(0., 1., 0.), r.origin);
+        assert_tuple_eq!(vec(0., 0., -1.), r.direction);
+        assert_eq!(0.5, r.time);
+
+        // moving the start keeps the same end
+        c.set_view_transform(translation(0., 2., 0.));
+        assert_tuple_eq!(
+            point(0., 0., 0.),
+            c.ray_for_canvas_point(5.5, 5.5, 0.5).origin
+        );
+    }
+
+    #[test]
+    fn turning_cameras_keep_their_shape() {
+        let mut c = Camera::from_size(11, 11, PI / 2.);
+        let still = c.ray_for_canvas_point(0., 0., 0.);
+        c.try_set_end_view_transform(Some(rotation_y(PI * 0.9)))
+            .unwrap();
+
+        // half way through, the camera has turned by 81 degrees rather than being squashed
+        let r = c.ray_for_canvas_point(0., 0., 0.5);
+        assert_tuple_eq!(point(0., 0., 0.), r.origin, epsilon = 0.0001);
+        assert_tuple_eq!(
+            rotation_y(-PI * 0.45) * still.direction,
+            r.direction,
+            epsilon = 0.0001
+        );
+    }
+
+    #[test]
+    fn sampling_random_times_blurs_moving_objects() {
+        let mut s = Sphere::unit();
+        // the sphere moves right across the middle of the image while the shutter is open
+        s.set_motion(Some(Motion::new(
+            translation(-2., 0., 0.),
+            translation(2., 0., 0.),
+        )));
+        let w = World::new(
+            vec![Box::new(s)],
+            vec![PointLight::new(white(), point(0., 0., -10.))],
+        );
+        let mut c = Camera::new(
+            11,
+            11,
+            PI / 2.,
+            view_transform(point(0., 0., -5.), point(0., 0., 0.), vec(0., 1., 0.)),
+        );
+
+        // with one sample, the sphere is only seen at the start of the interval
+        let mut still = Framebuffer::new(11, 11);
+        c.render_to(&w, &mut still);
+        assert_eq!(0., still.alpha_at(5, 5));
+
+        // but with many samples it's smeared across the whole path
+        c.samples_per_pixel = 32;
+        let mut blurred = Framebuffer::new(11, 11);
+        c.render_to(&w, &mut blurred);
+        let coverage = blurred.alpha_at(5, 5);
+        assert!(coverage > 0.2 && coverage < 0.8, "{}", coverage);
+        assert!(blurred.pixel_at(5, 5).r > 0.);
+    }
 }
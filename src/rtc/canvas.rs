@@ -2,166 +2,354 @@ use crate::*;
 
 use std::fs;
 use std::io;
-use std::path::Path;
+use std::io::{Read, Write};
 use std::vec::Vec;
 
 extern crate png;
 
-type WriteResult = Result<(), Box<dyn std::error::Error>>;
+mod framebuffer;
+pub use self::framebuffer::*;
+mod exr;
+pub use self::exr::*;
+mod hdr;
+pub use self::hdr::*;
+mod pfm;
+pub use self::pfm::*;
+mod ppm;
+pub use self::ppm::*;
 
-pub trait Canvas {
-    fn write_pixel(&mut self, c: &Color, x: usize, y: usize);
-}
+mod error;
+pub use self::error::*;
 
-pub struct PpmCanvas {
-    width: usize,
-    height: usize,
-    grid: Vec<Vec<Color>>,
-}
+type WriteResult = Result<(), CanvasError>;
+type ReadResult<T> = Result<T, CanvasError>;
 
-impl PpmCanvas {
-    fn to_ppm(&self) -> String {
-        let mut pixel_data = vec![vec!["0".to_string(); self.width]; self.height];
+// creates (or replaces) a file and writes to it through a buffer
+fn write_file<F>(filename: &str, write: F) -> WriteResult
+where
+    F: FnOnce(&mut io::BufWriter<fs::File>) -> WriteResult,
+{
+    let mut w = io::BufWriter::new(fs::File::create(filename)?);
+    write(&mut w)?;
+    Ok(w.flush()?)
+}
 
-        for y in 0..self.height {
-            for x in 0..self.width {
-                let p = self.grid[y][x].clamp().to_u8();
-                pixel_data[y][x] = format!("{} {} {}", p.0, p.1, p.2)
-            }
-        }
+fn invalid<S: Into<String>>(message: S) -> CanvasError {
+    CanvasError::InvalidFormat(message.into())
+}
 
-        format!(
-            "P3
-{width} {height}
-255
-{data}
-",
-            width = self.width,
-            height = self.height,
-            data = pixel_data
-                .iter()
-                .map(|x| x.join(" "))
-                .collect::<Vec<_>>()
-                .join("\n")
-        )
-    }
+pub trait Canvas {
+    fn write_pixel(&mut self, c: &Color, x: usize, y: usize);
 
-    pub fn new(width: usize, height: usize) -> Self {
-        let grid = vec![vec![black(); width]; height];
-        PpmCanvas {
-            width,
-            height,
-            grid,
-        }
-    }
-    pub fn write_to_file(&self, filename: &str) -> WriteResult {
-        Ok(fs::write(filename, self.to_ppm())?)
-    }
+    // how much of the pixel is covered by objects in the scene, from 0 (nothing was hit) to 1.
+    // most canvases have no use for this, so it's ignored by default
+    fn write_alpha(&mut self, _alpha: Float, _x: usize, _y: usize) {}
 }
 
-impl Canvas for PpmCanvas {
-    fn write_pixel(&mut self, c: &Color, x: usize, y: usize) {
-        &self.grid[y][x].set(c);
-    }
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PngBitDepth {
+    Eight,
+    Sixteen,
 }
 
 pub struct PngCanvas {
-    width: usize,
-    height: usize,
-    grid: Vec<Vec<Color>>,
+    buffer: Framebuffer,
+    display: DisplayTransform,
+    bit_depth: PngBitDepth,
+    // whether to write an alpha channel from the framebuffer's coverage values
+    alpha: bool,
 }
 impl PngCanvas {
     pub fn new(width: usize, height: usize) -> Self {
-        let grid = vec![vec![black(); width]; height];
-        PngCanvas {
-            width,
-            height,
-            grid,
-        }
+        PngCanvas::from(Framebuffer::new(width, height))
+    }
+
+    // how colors are converted into 8 or 16 bit values, see `DisplayTransform`
+    pub fn with_display_transform(mut self, display: DisplayTransform) -> Self {
+        self.display = display;
+        self
+    }
+
+    pub fn with_bit_depth(mut self, bit_depth: PngBitDepth) -> Self {
+        self.bit_depth = bit_depth;
+        self
+    }
+
+    pub fn with_alpha(mut self, alpha: bool) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    pub fn buffer(&self) -> &Framebuffer {
+        &self.buffer
     }
+
     pub fn write_to_file(&self, filename: &str) -> WriteResult {
-        // based on code at https://docs.rs/png/0.15.2/png/index.html#using-the-encoder
-        let path = Path::new(filename);
-        let file = fs::File::create(path)?;
-        let ref mut w = io::BufWriter::new(file);
+        write_file(filename, |w| self.write_to(w))
+    }
 
-        let mut encoder = png::Encoder::new(w, self.width as u32, self.height as u32);
-        encoder.set_color(png::ColorType::RGB);
-        encoder.set_depth(png::BitDepth::Eight);
+    pub fn write_to<W: Write>(&self, w: &mut W) -> WriteResult {
+        // based on code at https://docs.rs/png/0.15.2/png/index.html#using-the-encoder
+        let width = self.buffer.width() as u32;
+        let height = self.buffer.height() as u32;
+        let mut encoder = png::Encoder::new(w, width, height);
+        encoder.set_color(if self.alpha {
+            png::ColorType::RGBA
+        } else {
+            png::ColorType::RGB
+        });
+        encoder.set_depth(match self.bit_depth {
+            PngBitDepth::Eight => png::BitDepth::Eight,
+            PngBitDepth::Sixteen => png::BitDepth::Sixteen,
+        });
         let mut writer = encoder.write_header()?;
 
         let mut data = Vec::<u8>::new();
 
-        // todo: could simplify this if PngCanvas owns its own data
-        for y in 0..self.height {
-            for x in 0..self.width {
-                let p = self.grid[y][x].clamp().to_u8();
-                data.push(p.0);
-                data.push(p.1);
-                data.push(p.2);
+        for (x, y, c) in self.buffer.pixels() {
+            let alpha = self.buffer.alpha_at(x, y).clamp(0., 1.);
+            // partially covered pixels have already been blended with the (black) background,
+            // but PNG expects colors which haven't been multiplied by alpha yet
+            let c = if self.alpha && alpha > 0. {
+                c * (1. / alpha)
+            } else {
+                c
+            };
+
+            match self.bit_depth {
+                PngBitDepth::Eight => {
+                    let p = self.display.to_u8(&c);
+                    data.extend_from_slice(&[p.0, p.1, p.2]);
+                    if self.alpha {
+                        data.push((alpha * 255.).round() as u8);
+                    }
+                }
+                PngBitDepth::Sixteen => {
+                    // 16 bit samples are stored big-endian
+                    let p = self.display.to_u16(&c);
+                    data.extend_from_slice(&p.0.to_be_bytes());
+                    data.extend_from_slice(&p.1.to_be_bytes());
+                    data.extend_from_slice(&p.2.to_be_bytes());
+                    if self.alpha {
+                        data.extend_from_slice(&((alpha * 65535.).round() as u16).to_be_bytes());
+                    }
+                }
             }
         }
 
         Ok(writer.write_image_data(&data)?)
     }
+
+    // reads any kind of PNG file. Like `PpmCanvas::read_from`, values are just scaled to
+    // [0, 1] and not converted from sRGB or any other encoding.
+    pub fn read_from<R: Read>(r: R) -> ReadResult<PngCanvas> {
+        let mut decoder = png::Decoder::new(r);
+        // turn palettes and small grey values into regular 8 bit samples, but keep 16 bit ones
+        decoder.set_transformations(png::Transformations::EXPAND);
+        let (info, mut reader) = decoder.read_info()?;
+        let (color_type, bit_depth) = reader.output_color_type();
+
+        let mut data = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut data)?;
+
+        let (bit_depth, sample_size, max_value) = match bit_depth {
+            png::BitDepth::Eight => (PngBitDepth::Eight, 1, 255.),
+            png::BitDepth::Sixteen => (PngBitDepth::Sixteen, 2, 65535.),
+            _ => {
+                return Err(invalid(format!(
+                    "unsupported png bit depth {:?}",
+                    bit_depth
+                )))
+            }
+        };
+        let channels = color_type.samples();
+        let has_alpha =
+            color_type == png::ColorType::GrayscaleAlpha || color_type == png::ColorType::RGBA;
+
+        let width = info.width as usize;
+        let height = info.height as usize;
+        let mut buffer = Framebuffer::new(width, height);
+        for (y, row) in data.chunks(info.line_size).take(height).enumerate() {
+            for x in 0..width {
+                let sample = |channel: usize| {
+                    let i = (x * channels + channel) * sample_size;
+                    let value = row[i..i + sample_size]
+                        .iter()
+                        .fold(0, |acc, b| (acc << 8) | *b as u32);
+                    value as Float / max_value
+                };
+
+                let c = match channels {
+                    1 | 2 => grey(sample(0)),
+                    _ => Color::new(sample(0), sample(1), sample(2)),
+                };
+                let alpha = if has_alpha { sample(channels - 1) } else { 1. };
+                // undo the division by alpha that happens when writing
+                buffer.write_pixel(&(c * alpha), x, y);
+                buffer.write_alpha(alpha, x, y);
+            }
+        }
+
+        Ok(PngCanvas::from(buffer)
+            .with_bit_depth(bit_depth)
+            .with_alpha(has_alpha))
+    }
+
+    pub fn read_from_file(filename: &str) -> ReadResult<PngCanvas> {
+        PngCanvas::read_from(io::BufReader::new(fs::File::open(filename)?))
+    }
+}
+
+impl From<Framebuffer> for PngCanvas {
+    fn from(buffer: Framebuffer) -> PngCanvas {
+        PngCanvas {
+            buffer,
+            display: DisplayTransform::default(),
+            bit_depth: PngBitDepth::Eight,
+            alpha: false,
+        }
+    }
 }
 
 impl Canvas for PngCanvas {
     fn write_pixel(&mut self, c: &Color, x: usize, y: usize) {
-        self.grid[y][x].set(c);
+        self.buffer.write_pixel(c, x, y);
+    }
+
+    fn write_alpha(&mut self, alpha: Float, x: usize, y: usize) {
+        self.buffer.write_alpha(alpha, x, y);
     }
 }
 
 #[cfg(test)]
 mod tests {
-    mod ppmcanvas {
-        use super::super::*;
-
-        #[test]
-        fn can_create_basic_ppm_header() {
-            let c = PpmCanvas::new(5, 3);
-            let ppm = c.to_ppm();
-            let header_lines = ppm.split("\n").take(3).collect::<Vec<_>>();
-
-            // the first line specifies the type of netpbm file
-            assert_eq!("P3", header_lines[0]);
-            // the second line defines the size of the image
-            assert_eq!("5 3", header_lines[1]);
-            // the third line defines the maximum value of the pixel data
-            assert_eq!("255", header_lines[2]);
-        }
+    use super::*;
+
+    fn to_png(c: &PngCanvas) -> Vec<u8> {
+        let mut data = vec![];
+        c.write_to(&mut data).unwrap();
+        data
+    }
 
-        #[test]
-        fn can_write_ppm_pixel_data() {
-            let mut c = PpmCanvas::new(5, 3);
-            let c1 = Color::new(1.5, 0.0, 0.0);
-            let c2 = Color::new(0.0, 0.5, 0.0);
-            let c3 = Color::new(-0.5, 0.0, 1.0);
+    fn decode(data: &[u8]) -> (png::OutputInfo, Vec<u8>) {
+        let mut decoder = png::Decoder::new(data);
+        decoder.set_transformations(png::Transformations::IDENTITY);
+        let (info, mut reader) = decoder.read_info().unwrap();
+        let mut pixels = vec![0; info.buffer_size()];
+        reader.next_frame(&mut pixels).unwrap();
+        (info, pixels)
+    }
+
+    #[test]
+    fn png_round_trips() {
+        let mut c = PngCanvas::new(3, 2);
+        c.write_pixel(&Color::new(1., 0.2, 0.4), 2, 1);
+        c.write_pixel(&red(), 0, 0);
+
+        let c2 = PngCanvas::read_from(&to_png(&c)[..]).unwrap();
+        assert_eq!(3, c2.buffer().width());
+        assert_eq!(2, c2.buffer().height());
+        assert_eq!(red(), c2.buffer().pixel_at(0, 0));
+        assert_color_eq!(
+            Color::new(1., 0.2, 0.4),
+            c2.buffer().pixel_at(2, 1),
+            epsilon = 0.002
+        );
+        assert_eq!(1., c2.buffer().alpha_at(2, 1));
+    }
+
+    #[test]
+    fn sixteen_bit_png_keeps_more_precision() {
+        let mut c = PngCanvas::new(1, 1).with_bit_depth(PngBitDepth::Sixteen);
+        c.write_pixel(&Color::new(0.5, 0.001, 0.), 0, 0);
+
+        let data = to_png(&c);
+        let (info, pixels) = decode(&data);
+        assert_eq!(png::BitDepth::Sixteen, info.bit_depth);
+        assert_eq!(png::ColorType::RGB, info.color_type);
+        // big-endian, 0.5 * 65535 rounds up to 32768
+        assert_eq!(vec![0x80, 0x00, 0x00, 0x42, 0x00, 0x00], pixels);
+
+        let c2 = PngCanvas::read_from(&data[..]).unwrap();
+        assert_color_eq!(
+            Color::new(0.5, 0.001, 0.),
+            c2.buffer().pixel_at(0, 0),
+            epsilon = 0.00001
+        );
+    }
+
+    #[test]
+    fn png_alpha_channel_comes_from_coverage() {
+        let mut c = PngCanvas::new(3, 1).with_alpha(true);
+        c.write_pixel(&white(), 0, 0);
+        c.write_alpha(0., 1, 0);
+        // a pixel which is half covered by a white object
+        c.write_pixel(&grey(0.5), 2, 0);
+        c.write_alpha(0.5, 2, 0);
 
-            c.write_pixel(&c1, 0, 0);
-            c.write_pixel(&c2, 2, 1);
-            c.write_pixel(&c3, 4, 2);
+        let data = to_png(&c);
+        let (info, pixels) = decode(&data);
+        assert_eq!(png::ColorType::RGBA, info.color_type);
+        assert_eq!(
+            vec![255, 255, 255, 255, 0, 0, 0, 0, 255, 255, 255, 128],
+            pixels
+        );
 
-            let ppm = c.to_ppm();
-            let data_lines = ppm.split("\n").skip(3).take(3).collect::<Vec<_>>();
+        let c2 = PngCanvas::read_from(&data[..]).unwrap();
+        assert_eq!(0., c2.buffer().alpha_at(1, 0));
+        assert_color_eq!(grey(0.5), c2.buffer().pixel_at(2, 0), epsilon = 0.002);
+    }
 
-            assert_eq!("255 0 0 0 0 0 0 0 0 0 0 0 0 0 0", data_lines[0]);
-            assert_eq!("0 0 0 0 0 0 0 128 0 0 0 0 0 0 0", data_lines[1]);
-            assert_eq!("0 0 0 0 0 0 0 0 0 0 0 0 0 0 255", data_lines[2]);
+    #[test]
+    fn reading_greyscale_png() {
+        let mut data = vec![];
+        {
+            let mut encoder = png::Encoder::new(&mut data, 2, 1);
+            encoder.set_color(png::ColorType::Grayscale);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&[0, 51]).unwrap();
         }
 
-        #[test]
-        #[ignore]
-        fn ppm_pixel_data_wrapped_to_70_chars() {
-            unimplemented!();
+        let c = PngCanvas::read_from(&data[..]).unwrap();
+        assert_eq!(black(), c.buffer().pixel_at(0, 0));
+        assert_eq!(grey(0.2), c.buffer().pixel_at(1, 0));
+    }
+
+    #[test]
+    fn reading_invalid_png_fails() {
+        match PngCanvas::read_from(&b"P3\n1 1\n255\n0 0 0"[..]) {
+            Err(CanvasError::PngDecoding(_)) => (),
+            other => panic!("expected a png decoding error, got {:?}", other.err()),
         }
+    }
 
-        #[test]
-        fn ppm_ends_with_newline_char() {
-            let c = PpmCanvas::new(5, 3);
-            let ppm = c.to_ppm();
+    struct BrokenPipe;
+    impl Write for BrokenPipe {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "closed"))
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
 
-            assert_eq!('\n', ppm.chars().last().unwrap());
+    #[test]
+    fn write_errors_are_passed_on() {
+        let mut w = BrokenPipe;
+        match PpmCanvas::new(1, 1).write_to(&mut w) {
+            Err(CanvasError::Io(e)) => assert_eq!(io::ErrorKind::BrokenPipe, e.kind()),
+            other => panic!("expected an i/o error, got {:?}", other),
         }
+        assert!(HdrCanvas::new(1, 1).write_to(&mut w).is_err());
+        assert!(PfmCanvas::new(1, 1).write_to(&mut w).is_err());
+        assert!(ExrCanvas::new(1, 1).write_to(&mut w).is_err());
+        assert!(PngCanvas::new(1, 1).write_to(&mut w).is_err());
+    }
+
+    #[test]
+    fn errors_describe_what_went_wrong() {
+        let e = PpmCanvas::read_from(&b"P3\n1 1\n0\n"[..]).err().unwrap();
+        assert_eq!("invalid image: invalid maximum value 0", e.to_string());
     }
 }
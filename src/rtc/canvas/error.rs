@@ -0,0 +1,53 @@
+use super::*;
+use std::error::Error;
+use std::fmt;
+
+// everything that can go wrong when reading or writing an image
+#[derive(Debug)]
+pub enum CanvasError {
+    Io(io::Error),
+    PngEncoding(png::EncodingError),
+    PngDecoding(png::DecodingError),
+    // the data isn't in the expected format, or uses a feature we don't support
+    InvalidFormat(String),
+}
+
+impl fmt::Display for CanvasError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CanvasError::Io(e) => write!(f, "i/o error: {}", e),
+            CanvasError::PngEncoding(e) => write!(f, "error encoding png: {}", e),
+            CanvasError::PngDecoding(e) => write!(f, "error decoding png: {}", e),
+            CanvasError::InvalidFormat(message) => write!(f, "invalid image: {}", message),
+        }
+    }
+}
+
+impl Error for CanvasError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CanvasError::Io(e) => Some(e),
+            CanvasError::PngEncoding(e) => Some(e),
+            CanvasError::PngDecoding(e) => Some(e),
+            CanvasError::InvalidFormat(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for CanvasError {
+    fn from(e: io::Error) -> CanvasError {
+        CanvasError::Io(e)
+    }
+}
+
+impl From<png::EncodingError> for CanvasError {
+    fn from(e: png::EncodingError) -> CanvasError {
+        CanvasError::PngEncoding(e)
+    }
+}
+
+impl From<png::DecodingError> for CanvasError {
+    fn from(e: png::DecodingError) -> CanvasError {
+        CanvasError::PngDecoding(e)
+    }
+}
@@ -0,0 +1,172 @@
+use super::*;
+
+// writes OpenEXR (.exr) files: uncompressed scanlines of 32 bit float RGB data.
+// see https://www.openexr.com/documentation/openexrfilelayout.pdf
+pub struct ExrCanvas {
+    buffer: Framebuffer,
+}
+
+impl ExrCanvas {
+    pub fn new(width: usize, height: usize) -> Self {
+        ExrCanvas::from(Framebuffer::new(width, height))
+    }
+
+    pub fn buffer(&self) -> &Framebuffer {
+        &self.buffer
+    }
+
+    pub fn write_to<W: Write>(&self, w: &mut W) -> WriteResult {
+        let (width, height) = (self.buffer.width(), self.buffer.height());
+        let mut data = vec![];
+        // magic number, then version 2 with no flags set (ie a single part scanline image)
+        data.extend_from_slice(&20000630_i32.to_le_bytes());
+        data.extend_from_slice(&2_i32.to_le_bytes());
+
+        // channels must be listed in alphabetical order
+        let mut channels = vec![];
+        for name in &["B", "G", "R"] {
+            channels.extend_from_slice(name.as_bytes());
+            channels.push(0);
+            channels.extend_from_slice(&PIXEL_TYPE_FLOAT.to_le_bytes());
+            // pLinear and three reserved bytes
+            channels.extend_from_slice(&[0, 0, 0, 0]);
+            // x and y sampling
+            channels.extend_from_slice(&1_i32.to_le_bytes());
+            channels.extend_from_slice(&1_i32.to_le_bytes());
+        }
+        channels.push(0);
+
+        let mut window = vec![];
+        for v in &[0, 0, width as i32 - 1, height as i32 - 1] {
+            window.extend_from_slice(&v.to_le_bytes());
+        }
+
+        write_attribute(&mut data, "channels", "chlist", &channels);
+        write_attribute(&mut data, "compression", "compression", &[0]);
+        write_attribute(&mut data, "dataWindow", "box2i", &window);
+        write_attribute(&mut data, "displayWindow", "box2i", &window);
+        write_attribute(&mut data, "lineOrder", "lineOrder", &[0]);
+        write_attribute(&mut data, "pixelAspectRatio", "float", &1_f32.to_le_bytes());
+        write_attribute(&mut data, "screenWindowCenter", "v2f", &[0; 8]);
+        write_attribute(
+            &mut data,
+            "screenWindowWidth",
+            "float",
+            &1_f32.to_le_bytes(),
+        );
+        // end of header
+        data.push(0);
+
+        // each scanline is stored as a separate chunk, which we need to list
+        // the file offsets of before writing the chunks themselves
+        let line_size = width * 3 * 4;
+        let chunk_size = 4 + 4 + line_size;
+        let first_chunk = data.len() + height * 8;
+        for y in 0..height {
+            data.extend_from_slice(&((first_chunk + y * chunk_size) as u64).to_le_bytes());
+        }
+        w.write_all(&data)?;
+
+        for (y, row) in self.buffer.rows().enumerate() {
+            let mut data = Vec::with_capacity(chunk_size);
+            data.extend_from_slice(&(y as i32).to_le_bytes());
+            data.extend_from_slice(&(line_size as i32).to_le_bytes());
+            // all of the values for one channel, followed by the next one
+            for channel in &[|c: &Color| c.b, |c: &Color| c.g, |c: &Color| c.r] {
+                for c in row {
+                    data.extend_from_slice(&to_f32(channel(c)).to_le_bytes());
+                }
+            }
+            w.write_all(&data)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn write_to_file(&self, filename: &str) -> WriteResult {
+        write_file(filename, |w| self.write_to(w))
+    }
+}
+
+impl From<Framebuffer> for ExrCanvas {
+    fn from(buffer: Framebuffer) -> ExrCanvas {
+        ExrCanvas { buffer }
+    }
+}
+
+impl Canvas for ExrCanvas {
+    fn write_pixel(&mut self, c: &Color, x: usize, y: usize) {
+        self.buffer.write_pixel(c, x, y);
+    }
+}
+
+const PIXEL_TYPE_FLOAT: i32 = 2;
+
+fn write_attribute(data: &mut Vec<u8>, name: &str, type_name: &str, value: &[u8]) {
+    data.extend_from_slice(name.as_bytes());
+    data.push(0);
+    data.extend_from_slice(type_name.as_bytes());
+    data.push(0);
+    data.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    data.extend_from_slice(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_exr(c: &ExrCanvas) -> Vec<u8> {
+        let mut data = vec![];
+        c.write_to(&mut data).unwrap();
+        data
+    }
+
+    fn read_i32(data: &[u8], offset: usize) -> i32 {
+        i32::from_le_bytes([
+            data[offset],
+            data[offset + 1],
+            data[offset + 2],
+            data[offset + 3],
+        ])
+    }
+
+    fn read_f32(data: &[u8], offset: usize) -> f32 {
+        f32::from_bits(read_i32(data, offset) as u32)
+    }
+
+    #[test]
+    fn exr_starts_with_magic_number_and_version() {
+        let c = ExrCanvas::new(5, 3);
+        let exr = to_exr(&c);
+        assert_eq!(&[0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0], &exr[..8]);
+    }
+
+    #[test]
+    fn exr_offset_table_points_at_each_scanline() {
+        let c = ExrCanvas::new(5, 3);
+        let exr = to_exr(&c);
+        let chunk_size = 8 + 5 * 3 * 4;
+        let first_chunk = exr.len() - 3 * chunk_size;
+        let offsets_start = first_chunk - 3 * 8;
+
+        for y in 0..3 {
+            let offset = read_i32(&exr, offsets_start + y * 8) as usize;
+            assert_eq!(first_chunk + y * chunk_size, offset);
+            assert_eq!(y as i32, read_i32(&exr, offset));
+            assert_eq!(5 * 3 * 4, read_i32(&exr, offset + 4));
+        }
+    }
+
+    #[test]
+    fn exr_stores_unclamped_floats_by_channel() {
+        let mut c = ExrCanvas::new(2, 1);
+        c.write_pixel(&Color::new(1.5, 20., -0.5), 1, 0);
+        let exr = to_exr(&c);
+        let line = exr.len() - 2 * 3 * 4;
+        let floats = (0..6)
+            .map(|i| read_f32(&exr, line + i * 4))
+            .collect::<Vec<_>>();
+        // B, B, G, G, R, R
+        assert_eq!(vec![0., -0.5, 0., 20., 0., 1.5], floats);
+    }
+}
@@ -0,0 +1,140 @@
+use super::*;
+
+// an in-memory image of linear, unclamped colors. The various file
+// canvases are thin wrappers around one of these which encode it on write.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+    // stored row by row, starting from the top left
+    pixels: Vec<Color>,
+    // coverage for each pixel, in the same order as `pixels`
+    alpha: Vec<Float>,
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Framebuffer {
+        Framebuffer {
+            width,
+            height,
+            pixels: vec![black(); width * height],
+            alpha: vec![1.; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixel_at(&self, x: usize, y: usize) -> Color {
+        self.pixels[self.index(x, y)]
+    }
+
+    pub fn alpha_at(&self, x: usize, y: usize) -> Float {
+        self.alpha[self.index(x, y)]
+    }
+
+    // each row of pixels in turn, from top to bottom
+    pub fn rows(&self) -> std::slice::Chunks<'_, Color> {
+        // chunks() panics on a zero chunk size, so give empty images a nonzero one
+        self.pixels.chunks(self.width.max(1))
+    }
+
+    // every pixel along with its (x, y) coordinates, in the same order as `rows()`
+    pub fn pixels(&self) -> impl Iterator<Item = (usize, usize, Color)> + '_ {
+        let width = self.width;
+        self.pixels
+            .iter()
+            .enumerate()
+            .map(move |(i, c)| (i % width, i / width, *c))
+    }
+
+    fn index(&self, x: usize, y: usize) -> usize {
+        assert!(x < self.width && y < self.height);
+        y * self.width + x
+    }
+}
+
+impl Canvas for Framebuffer {
+    fn write_pixel(&mut self, c: &Color, x: usize, y: usize) {
+        let i = self.index(x, y);
+        self.pixels[i] = *c;
+    }
+
+    fn write_alpha(&mut self, alpha: Float, x: usize, y: usize) {
+        let i = self.index(x, y);
+        self.alpha[i] = alpha;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_framebuffer_is_black() {
+        let f = Framebuffer::new(10, 20);
+        assert_eq!(10, f.width());
+        assert_eq!(20, f.height());
+        assert!(f.pixels().all(|(_, _, c)| c == black()));
+    }
+
+    #[test]
+    fn pixels_can_be_read_back() {
+        let mut f = Framebuffer::new(10, 20);
+        f.write_pixel(&Color::new(2., -1., 0.5), 2, 3);
+        assert_eq!(Color::new(2., -1., 0.5), f.pixel_at(2, 3));
+        assert_eq!(black(), f.pixel_at(3, 2));
+    }
+
+    #[test]
+    fn framebuffer_is_opaque_until_alpha_is_written() {
+        let mut f = Framebuffer::new(2, 2);
+        f.write_alpha(0.25, 1, 0);
+        assert_eq!(1., f.alpha_at(0, 0));
+        assert_eq!(0.25, f.alpha_at(1, 0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn reading_outside_the_framebuffer_panics() {
+        let f = Framebuffer::new(10, 20);
+        f.pixel_at(10, 0);
+    }
+
+    #[test]
+    fn iterating_goes_row_by_row() {
+        let mut f = Framebuffer::new(2, 2);
+        f.write_pixel(&red(), 1, 0);
+        f.write_pixel(&white(), 0, 1);
+
+        let rows = f.rows().collect::<Vec<_>>();
+        assert_eq!(vec![&[black(), red()][..], &[white(), black()][..]], rows);
+
+        let pixels = f.pixels().collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (0, 0, black()),
+                (1, 0, red()),
+                (0, 1, white()),
+                (1, 1, black())
+            ],
+            pixels
+        );
+    }
+
+    #[test]
+    fn framebuffer_can_be_converted_to_file_canvases() {
+        let mut f = Framebuffer::new(2, 1);
+        f.write_pixel(&Color::new(1.5, 0., 0.), 1, 0);
+
+        let ppm = PpmCanvas::from(f.clone());
+        assert_eq!(&f, ppm.buffer());
+        let exr = ExrCanvas::from(f.clone());
+        assert_eq!(&f, exr.buffer());
+    }
+}
@@ -0,0 +1,194 @@
+use super::*;
+
+// writes Radiance RGBE (.hdr) files, which keep the full range of each color
+// by storing a shared exponent alongside 8 bits of mantissa per channel.
+// see http://radsite.lbl.gov/radiance/refer/filefmts.pdf
+pub struct HdrCanvas {
+    buffer: Framebuffer,
+}
+
+impl HdrCanvas {
+    pub fn new(width: usize, height: usize) -> Self {
+        HdrCanvas::from(Framebuffer::new(width, height))
+    }
+
+    pub fn buffer(&self) -> &Framebuffer {
+        &self.buffer
+    }
+
+    pub fn write_to<W: Write>(&self, w: &mut W) -> WriteResult {
+        write!(
+            w,
+            "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+            self.buffer.height(),
+            self.buffer.width()
+        )?;
+
+        let mut data = vec![];
+        for row in self.buffer.rows() {
+            let scanline = row.iter().map(to_rgbe).collect::<Vec<_>>();
+            data.clear();
+            write_scanline(&mut data, &scanline);
+            w.write_all(&data)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn write_to_file(&self, filename: &str) -> WriteResult {
+        write_file(filename, |w| self.write_to(w))
+    }
+}
+
+impl From<Framebuffer> for HdrCanvas {
+    fn from(buffer: Framebuffer) -> HdrCanvas {
+        HdrCanvas { buffer }
+    }
+}
+
+impl Canvas for HdrCanvas {
+    fn write_pixel(&mut self, c: &Color, x: usize, y: usize) {
+        self.buffer.write_pixel(c, x, y);
+    }
+}
+
+fn to_rgbe(c: &Color) -> [u8; 4] {
+    // negative values can't be represented, so treat them as black
+    let (r, g, b) = (c.r.max(0.), c.g.max(0.), c.b.max(0.));
+    let v = r.max(g).max(b);
+    if v < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+
+    // find the exponent such that v = mantissa * 2^exponent, with mantissa in [0.5, 1)
+    let mut exponent = v.log2().floor() as i32 + 1;
+    let mut mantissa = v / Float::powi(2., exponent);
+    // log2 can be slightly off for exact powers of two
+    if mantissa >= 1. {
+        mantissa /= 2.;
+        exponent += 1;
+    }
+    let scale = mantissa * 256. / v;
+
+    [
+        (r * scale) as u8,
+        (g * scale) as u8,
+        (b * scale) as u8,
+        (exponent + 128) as u8,
+    ]
+}
+
+fn write_scanline(data: &mut Vec<u8>, scanline: &[[u8; 4]]) {
+    let width = scanline.len();
+    // run length encoding is only defined for scanlines in this range
+    if !(8..0x8000).contains(&width) {
+        for pixel in scanline {
+            data.extend_from_slice(pixel);
+        }
+        return;
+    }
+
+    data.extend_from_slice(&[2, 2, (width >> 8) as u8, (width & 0xff) as u8]);
+    // each channel is run length encoded separately
+    for channel in 0..4 {
+        let values = scanline.iter().map(|p| p[channel]).collect::<Vec<_>>();
+        write_rle_channel(data, &values);
+    }
+}
+
+// runs of identical bytes are written as (128 + run length, value), and
+// everything else as (count, values...)
+fn write_rle_channel(data: &mut Vec<u8>, values: &[u8]) {
+    const MIN_RUN: usize = 4;
+    let mut i = 0;
+    while i < values.len() {
+        // look for the start of the next run that's long enough to be worth encoding
+        let mut run_start = i;
+        let mut run_length = 0;
+        while run_start < values.len() {
+            run_length = 1;
+            while run_length < 127
+                && run_start + run_length < values.len()
+                && values[run_start + run_length] == values[run_start]
+            {
+                run_length += 1;
+            }
+            if run_length >= MIN_RUN {
+                break;
+            }
+            run_start += run_length;
+        }
+
+        // write out the literal values before the run
+        while i < run_start {
+            let count = (run_start - i).min(128);
+            data.push(count as u8);
+            data.extend_from_slice(&values[i..i + count]);
+            i += count;
+        }
+
+        if run_length >= MIN_RUN {
+            data.push(128 + run_length as u8);
+            data.push(values[run_start]);
+            i = run_start + run_length;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_hdr(c: &HdrCanvas) -> Vec<u8> {
+        let mut data = vec![];
+        c.write_to(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn rgbe_shares_an_exponent_between_channels() {
+        assert_eq!([128, 64, 32, 129], to_rgbe(&Color::new(1., 0.5, 0.25)));
+        assert_eq!([128, 64, 32, 130], to_rgbe(&Color::new(2., 1., 0.5)));
+        // values well above 1.0 are preserved
+        assert_eq!([128, 0, 0, 128 + 11], to_rgbe(&Color::new(1024., 0., 0.)));
+        assert_eq!([0, 0, 0, 0], to_rgbe(&black()));
+        assert_eq!([0, 0, 0, 0], to_rgbe(&Color::new(-1., -2., 0.)));
+    }
+
+    #[test]
+    fn hdr_header_describes_image_size() {
+        let c = HdrCanvas::new(5, 3);
+        let hdr = to_hdr(&c);
+        assert!(hdr.starts_with(b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 3 +X 5\n"));
+    }
+
+    #[test]
+    fn narrow_images_are_written_without_run_length_encoding() {
+        let mut c = HdrCanvas::new(2, 1);
+        c.write_pixel(&Color::new(1., 0.5, 0.25), 1, 0);
+        let hdr = to_hdr(&c);
+        assert_eq!(&[0, 0, 0, 0, 128, 64, 32, 129], &hdr[hdr.len() - 8..]);
+    }
+
+    #[test]
+    fn runs_of_identical_values_are_compressed() {
+        let mut data = vec![];
+        write_rle_channel(&mut data, &[7; 10]);
+        assert_eq!(vec![128 + 10, 7], data);
+
+        let mut data2 = vec![];
+        write_rle_channel(&mut data2, &[1, 2, 3, 5, 5, 5, 5, 5, 9]);
+        assert_eq!(vec![3, 1, 2, 3, 128 + 5, 5, 1, 9], data2);
+    }
+
+    #[test]
+    fn wide_scanlines_start_with_rle_marker() {
+        let c = HdrCanvas::new(10, 1);
+        let hdr = to_hdr(&c);
+        // marker and width, then each channel of the black scanline is a single run
+        assert_eq!(
+            &[2, 2, 0, 10, 138, 0, 138, 0, 138, 0, 138, 0],
+            &hdr[hdr.len() - 12..]
+        );
+    }
+}
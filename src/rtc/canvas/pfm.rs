@@ -0,0 +1,88 @@
+use super::*;
+
+// writes Portable Float Map (.pfm) files: a PPM-like header followed by
+// raw 32 bit floats for each channel, with the rows stored bottom-to-top.
+// see http://www.pauldebevec.com/Research/HDR/PFM/
+pub struct PfmCanvas {
+    buffer: Framebuffer,
+}
+
+impl PfmCanvas {
+    pub fn new(width: usize, height: usize) -> Self {
+        PfmCanvas::from(Framebuffer::new(width, height))
+    }
+
+    pub fn buffer(&self) -> &Framebuffer {
+        &self.buffer
+    }
+
+    pub fn write_to<W: Write>(&self, w: &mut W) -> WriteResult {
+        // a negative scale factor means that the data is little-endian
+        write!(
+            w,
+            "PF\n{} {}\n-1.0\n",
+            self.buffer.width(),
+            self.buffer.height()
+        )?;
+
+        let mut data = vec![];
+        for row in self.buffer.rows().rev() {
+            data.clear();
+            for c in row {
+                data.extend_from_slice(&to_f32(c.r).to_le_bytes());
+                data.extend_from_slice(&to_f32(c.g).to_le_bytes());
+                data.extend_from_slice(&to_f32(c.b).to_le_bytes());
+            }
+            w.write_all(&data)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn write_to_file(&self, filename: &str) -> WriteResult {
+        write_file(filename, |w| self.write_to(w))
+    }
+}
+
+impl From<Framebuffer> for PfmCanvas {
+    fn from(buffer: Framebuffer) -> PfmCanvas {
+        PfmCanvas { buffer }
+    }
+}
+
+impl Canvas for PfmCanvas {
+    fn write_pixel(&mut self, c: &Color, x: usize, y: usize) {
+        self.buffer.write_pixel(c, x, y);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_pfm(c: &PfmCanvas) -> Vec<u8> {
+        let mut data = vec![];
+        c.write_to(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn pfm_header_describes_image_size_and_endianness() {
+        let c = PfmCanvas::new(5, 3);
+        let pfm = to_pfm(&c);
+        assert!(pfm.starts_with(b"PF\n5 3\n-1.0\n"));
+        assert_eq!(12 + 5 * 3 * 3 * 4, pfm.len());
+    }
+
+    #[test]
+    fn pfm_stores_unclamped_floats_from_the_bottom_row_up() {
+        let mut c = PfmCanvas::new(1, 2);
+        c.write_pixel(&Color::new(1.5, -0.5, 100.), 0, 1);
+        let pfm = to_pfm(&c);
+        let floats = pfm[12..]
+            .chunks(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect::<Vec<_>>();
+        assert_eq!(vec![1.5, -0.5, 100., 0., 0., 0.], floats);
+    }
+}
@@ -0,0 +1,450 @@
+use super::*;
+
+// see http://netpbm.sourceforge.net/doc/ppm.html
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PpmFormat {
+    // "plain" P3 files, with each value written as text
+    Ascii,
+    // "raw" P6 files, with each value written as a single byte
+    Binary,
+}
+
+pub struct PpmCanvas {
+    buffer: Framebuffer,
+    display: DisplayTransform,
+    format: PpmFormat,
+}
+
+// the PPM spec says that lines in plain files shouldn't be longer than this
+const MAX_LINE_LENGTH: usize = 70;
+
+impl PpmCanvas {
+    pub fn new(width: usize, height: usize) -> Self {
+        PpmCanvas::from(Framebuffer::new(width, height))
+    }
+
+    // how colors are converted into 8 bit values, see `DisplayTransform`
+    pub fn with_display_transform(mut self, display: DisplayTransform) -> Self {
+        self.display = display;
+        self
+    }
+
+    pub fn with_format(mut self, format: PpmFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn buffer(&self) -> &Framebuffer {
+        &self.buffer
+    }
+
+    pub fn write_to<W: Write>(&self, w: &mut W) -> WriteResult {
+        let magic = match self.format {
+            PpmFormat::Ascii => "P3",
+            PpmFormat::Binary => "P6",
+        };
+        writeln!(
+            w,
+            "{}\n{} {}\n255",
+            magic,
+            self.buffer.width(),
+            self.buffer.height()
+        )?;
+
+        match self.format {
+            PpmFormat::Ascii => self.write_ascii_data(w),
+            PpmFormat::Binary => self.write_binary_data(w),
+        }
+    }
+
+    fn write_ascii_data<W: Write>(&self, w: &mut W) -> WriteResult {
+        for row in self.buffer.rows() {
+            // each row starts on a new line, and long rows are wrapped between values
+            let mut line_length = 0;
+            for c in row {
+                let p = self.display.to_u8(c);
+                for value in &[p.0, p.1, p.2] {
+                    let value = value.to_string();
+                    if line_length == 0 {
+                        line_length = value.len();
+                    } else if line_length + 1 + value.len() > MAX_LINE_LENGTH {
+                        writeln!(w)?;
+                        line_length = value.len();
+                    } else {
+                        write!(w, " ")?;
+                        line_length += 1 + value.len();
+                    }
+                    write!(w, "{}", value)?;
+                }
+            }
+            writeln!(w)?;
+        }
+        Ok(())
+    }
+
+    fn write_binary_data<W: Write>(&self, w: &mut W) -> WriteResult {
+        for row in self.buffer.rows() {
+            let data = row
+                .iter()
+                .flat_map(|c| {
+                    let p = self.display.to_u8(c);
+                    vec![p.0, p.1, p.2]
+                })
+                .collect::<Vec<_>>();
+            w.write_all(&data)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_to_file(&self, filename: &str) -> WriteResult {
+        write_file(filename, |w| self.write_to(w))
+    }
+
+    // reads either a P3 or P6 file. Values are scaled to [0, 1] using the
+    // file's maximum value, but otherwise aren't converted at all.
+    pub fn read_from<R: Read>(mut r: R) -> ReadResult<PpmCanvas> {
+        let mut data = vec![];
+        r.read_to_end(&mut data)?;
+        let mut parser = PpmParser {
+            data: &data,
+            pos: 0,
+        };
+
+        let format = match parser.next_token()? {
+            b"P3" => PpmFormat::Ascii,
+            b"P6" => PpmFormat::Binary,
+            _ => return Err(invalid("not a P3 or P6 ppm file")),
+        };
+        let width = parser.next_number()?;
+        let height = parser.next_number()?;
+        if width == 0 || height == 0 {
+            return Err(invalid(format!("invalid size {}x{}", width, height)));
+        }
+        let max_value = parser.next_number()?;
+        if max_value == 0 || max_value > 65535 {
+            return Err(invalid(format!("invalid maximum value {}", max_value)));
+        }
+
+        if format == PpmFormat::Binary {
+            // exactly one whitespace character separates the header from the binary data
+            parser.pos += 1;
+        }
+
+        // the sizes come from the file, so check there's enough data for them before
+        // allocating anything. Each value takes at least 1 byte (or 2 in 16 bit files).
+        let bytes_per_value = match format {
+            PpmFormat::Binary if max_value > 255 => 2,
+            _ => 1,
+        };
+        let needed = width
+            .checked_mul(height)
+            .and_then(|pixels| pixels.checked_mul(3 * bytes_per_value));
+        let available = data.len().saturating_sub(parser.pos);
+        match needed {
+            Some(needed) if needed <= available => {}
+            _ => {
+                return Err(invalid(format!(
+                    "{}x{} is too big for the data in the ppm file",
+                    width, height
+                )))
+            }
+        }
+
+        let mut buffer = Framebuffer::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let mut channels = [0.; 3];
+                for channel in channels.iter_mut() {
+                    let value = match format {
+                        PpmFormat::Ascii => parser.next_number()?,
+                        PpmFormat::Binary => parser.next_binary_value(max_value)?,
+                    };
+                    if value > max_value {
+                        return Err(invalid(format!(
+                            "value {} is above maximum {}",
+                            value, max_value
+                        )));
+                    }
+                    *channel = value as Float / max_value as Float;
+                }
+                let c = Color::new(channels[0], channels[1], channels[2]);
+                buffer.write_pixel(&c, x, y);
+            }
+        }
+
+        Ok(PpmCanvas::from(buffer).with_format(format))
+    }
+
+    pub fn read_from_file(filename: &str) -> ReadResult<PpmCanvas> {
+        PpmCanvas::read_from(io::BufReader::new(fs::File::open(filename)?))
+    }
+}
+
+impl From<Framebuffer> for PpmCanvas {
+    fn from(buffer: Framebuffer) -> PpmCanvas {
+        PpmCanvas {
+            buffer,
+            display: DisplayTransform::default(),
+            format: PpmFormat::Ascii,
+        }
+    }
+}
+
+impl Canvas for PpmCanvas {
+    fn write_pixel(&mut self, c: &Color, x: usize, y: usize) {
+        self.buffer.write_pixel(c, x, y);
+    }
+}
+
+struct PpmParser<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> PpmParser<'a> {
+    // the next whitespace-separated token, skipping over any comments
+    fn next_token(&mut self) -> ReadResult<&'a [u8]> {
+        loop {
+            match self.data.get(self.pos) {
+                Some(b) if b.is_ascii_whitespace() => self.pos += 1,
+                // comments run from a # to the end of the line
+                Some(b'#') => {
+                    while self.pos < self.data.len() && self.data[self.pos] != b'\n' {
+                        self.pos += 1;
+                    }
+                }
+                Some(_) => break,
+                None => return Err(invalid("unexpected end of ppm file")),
+            }
+        }
+
+        let start = self.pos;
+        while self.pos < self.data.len()
+            && !self.data[self.pos].is_ascii_whitespace()
+            && self.data[self.pos] != b'#'
+        {
+            self.pos += 1;
+        }
+        Ok(&self.data[start..self.pos])
+    }
+
+    fn next_number(&mut self) -> ReadResult<usize> {
+        let token = self.next_token()?;
+        let text = String::from_utf8_lossy(token);
+        text.parse()
+            .map_err(|_| invalid(format!("expected a number, found {:?}", text)))
+    }
+
+    // binary values are one byte each, or two (big-endian) if the maximum value needs it
+    fn next_binary_value(&mut self, max_value: usize) -> ReadResult<usize> {
+        let size = if max_value < 256 { 1 } else { 2 };
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + size)
+            .ok_or_else(|| invalid("unexpected end of ppm file"))?;
+        self.pos += size;
+        Ok(bytes.iter().fold(0, |acc, b| (acc << 8) | *b as usize))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_ppm(c: &PpmCanvas) -> String {
+        let mut data = vec![];
+        c.write_to(&mut data).unwrap();
+        String::from_utf8(data).unwrap()
+    }
+
+    #[test]
+    fn can_create_basic_ppm_header() {
+        let c = PpmCanvas::new(5, 3);
+        let ppm = to_ppm(&c);
+        let header_lines = ppm.split("\n").take(3).collect::<Vec<_>>();
+
+        // the first line specifies the type of netpbm file
+        assert_eq!("P3", header_lines[0]);
+        // the second line defines the size of the image
+        assert_eq!("5 3", header_lines[1]);
+        // the third line defines the maximum value of the pixel data
+        assert_eq!("255", header_lines[2]);
+    }
+
+    #[test]
+    fn can_write_ppm_pixel_data() {
+        let mut c = PpmCanvas::new(5, 3);
+        let c1 = Color::new(1.5, 0.0, 0.0);
+        let c2 = Color::new(0.0, 0.5, 0.0);
+        let c3 = Color::new(-0.5, 0.0, 1.0);
+
+        c.write_pixel(&c1, 0, 0);
+        c.write_pixel(&c2, 2, 1);
+        c.write_pixel(&c3, 4, 2);
+
+        let ppm = to_ppm(&c);
+        let data_lines = ppm.split("\n").skip(3).take(3).collect::<Vec<_>>();
+
+        assert_eq!("255 0 0 0 0 0 0 0 0 0 0 0 0 0 0", data_lines[0]);
+        assert_eq!("0 0 0 0 0 0 0 128 0 0 0 0 0 0 0", data_lines[1]);
+        assert_eq!("0 0 0 0 0 0 0 0 0 0 0 0 0 0 255", data_lines[2]);
+    }
+
+    #[test]
+    fn ppm_pixel_data_uses_display_transform() {
+        let mut c = PpmCanvas::new(2, 1)
+            .with_display_transform(DisplayTransform::srgb(ToneMapper::Reinhard, 0.));
+        c.write_pixel(&Color::new(1., 3., 0.), 0, 0);
+
+        let ppm = to_ppm(&c);
+        let data_lines = ppm.split("\n").skip(3).take(1).collect::<Vec<_>>();
+
+        // reinhard maps 1 -> 0.5 and 3 -> 0.75, then sRGB brightens those
+        assert_eq!("188 225 0 0 0 0", data_lines[0]);
+    }
+
+    #[test]
+    fn ppm_pixel_data_wrapped_to_70_chars() {
+        let mut c = PpmCanvas::new(10, 2);
+        for y in 0..2 {
+            for x in 0..10 {
+                c.write_pixel(&Color::new(1., 0.8, 0.6), x, y);
+            }
+        }
+
+        let ppm = to_ppm(&c);
+        let data_lines = ppm.split("\n").skip(3).take(4).collect::<Vec<_>>();
+
+        assert_eq!(
+            "255 204 153 255 204 153 255 204 153 255 204 153 255 204 153 255 204",
+            data_lines[0]
+        );
+        assert_eq!(
+            "153 255 204 153 255 204 153 255 204 153 255 204 153",
+            data_lines[1]
+        );
+        assert_eq!(
+            "255 204 153 255 204 153 255 204 153 255 204 153 255 204 153 255 204",
+            data_lines[2]
+        );
+        assert_eq!(
+            "153 255 204 153 255 204 153 255 204 153 255 204 153",
+            data_lines[3]
+        );
+    }
+
+    #[test]
+    fn ppm_ends_with_newline_char() {
+        let c = PpmCanvas::new(5, 3);
+        let ppm = to_ppm(&c);
+
+        assert_eq!('\n', ppm.chars().last().unwrap());
+    }
+
+    #[test]
+    fn can_write_binary_ppm() {
+        let mut c = PpmCanvas::new(2, 1).with_format(PpmFormat::Binary);
+        c.write_pixel(&Color::new(1., 0.5, 0.), 1, 0);
+
+        let mut data = vec![];
+        c.write_to(&mut data).unwrap();
+        assert_eq!(b"P6\n2 1\n255\n\x00\x00\x00\xff\x80\x00", &data[..]);
+    }
+
+    #[test]
+    fn reading_plain_ppm() {
+        let ppm = "P3\n2 2\n255\n255 0 0 0 255 0\n0 0 255 255 255 255\n";
+        let c = PpmCanvas::read_from(ppm.as_bytes()).unwrap();
+        let b = c.buffer();
+
+        assert_eq!(2, b.width());
+        assert_eq!(2, b.height());
+        assert_eq!(red(), b.pixel_at(0, 0));
+        assert_eq!(Color::new(0., 1., 0.), b.pixel_at(1, 0));
+        assert_eq!(Color::new(0., 0., 1.), b.pixel_at(0, 1));
+        assert_eq!(white(), b.pixel_at(1, 1));
+    }
+
+    #[test]
+    fn reading_ppm_ignores_comments() {
+        let ppm = "# made by hand\nP3\n# size:\n1 1 # one pixel\n255\n# the pixel\n255 255 255\n";
+        let c = PpmCanvas::read_from(ppm.as_bytes()).unwrap();
+        assert_eq!(white(), c.buffer().pixel_at(0, 0));
+    }
+
+    #[test]
+    fn reading_ppm_allows_any_whitespace() {
+        let ppm = "P3\r\n  2\t1\n\n255   0\n0\t\t0\r\n255\n255 255";
+        let c = PpmCanvas::read_from(ppm.as_bytes()).unwrap();
+        assert_eq!(black(), c.buffer().pixel_at(0, 0));
+        assert_eq!(white(), c.buffer().pixel_at(1, 0));
+    }
+
+    #[test]
+    fn reading_ppm_scales_by_max_value() {
+        let ppm = "P3\n1 2\n100\n100 50 0\n20 0 0\n";
+        let c = PpmCanvas::read_from(ppm.as_bytes()).unwrap();
+        assert_eq!(Color::new(1., 0.5, 0.), c.buffer().pixel_at(0, 0));
+        assert_eq!(Color::new(0.2, 0., 0.), c.buffer().pixel_at(0, 1));
+    }
+
+    #[test]
+    fn reading_binary_ppm() {
+        let c = PpmCanvas::read_from(&b"P6\n2 1\n255\n\x00\x00\x00\xff\x80\x00"[..]).unwrap();
+        assert_eq!(black(), c.buffer().pixel_at(0, 0));
+        assert_eq!(Color::new(1., 128. / 255., 0.), c.buffer().pixel_at(1, 0));
+    }
+
+    #[test]
+    fn reading_16_bit_binary_ppm() {
+        let c = PpmCanvas::read_from(&b"P6 1 1 65535 \xff\xff\x80\x00\x00\x00"[..]).unwrap();
+        assert_eq!(
+            Color::new(1., 32768. / 65535., 0.),
+            c.buffer().pixel_at(0, 0)
+        );
+    }
+
+    #[test]
+    fn binary_ppm_round_trips() {
+        let mut c = PpmCanvas::new(3, 2).with_format(PpmFormat::Binary);
+        c.write_pixel(&Color::new(1., 0.2, 0.4), 2, 1);
+        let mut data = vec![];
+        c.write_to(&mut data).unwrap();
+
+        let c2 = PpmCanvas::read_from(&data[..]).unwrap();
+        assert_color_eq!(
+            Color::new(1., 0.2, 0.4),
+            c2.buffer().pixel_at(2, 1),
+            epsilon = 0.002
+        );
+    }
+
+    #[test]
+    fn reading_invalid_ppm_fails() {
+        assert!(PpmCanvas::read_from(&b"P5\n1 1\n255\n0"[..]).is_err());
+        assert!(PpmCanvas::read_from(&b"P3\n2 2\n255\n0 0 0"[..]).is_err());
+        assert!(PpmCanvas::read_from(&b"P3\n1 1\n255\n0 0 256"[..]).is_err());
+        assert!(PpmCanvas::read_from(&b"P3\none 1\n255\n0 0 0"[..]).is_err());
+        assert!(PpmCanvas::read_from(&b"P6\n1 1\n255\n\x00"[..]).is_err());
+    }
+
+    #[test]
+    fn huge_sizes_in_the_header_fail_without_allocating() {
+        // these would need far more memory than there is, or overflow working out how much
+        let huge = PpmCanvas::read_from(&b"P6\n100000 100000\n255\n\x00\x00\x00"[..]);
+        assert!(huge.err().unwrap().to_string().contains("too big"));
+        let overflow = format!("P3\n{} {}\n255\n0 0 0", usize::MAX, usize::MAX);
+        assert!(PpmCanvas::read_from(overflow.as_bytes()).is_err());
+        // 16 bit values take 2 bytes each
+        assert!(PpmCanvas::read_from(&b"P6\n1 1\n65535\n\x00\x00\x00"[..]).is_err());
+    }
+
+    #[test]
+    fn empty_sizes_in_the_header_are_rejected() {
+        // with nothing to check against the data, a huge height would take forever to read
+        let empty = format!("P3\n0 {}\n255\n", usize::MAX);
+        let e = PpmCanvas::read_from(empty.as_bytes()).err().unwrap();
+        assert!(e.to_string().contains("invalid size"));
+        assert!(PpmCanvas::read_from(&b"P6\n3 0\n255\n"[..]).is_err());
+    }
+}
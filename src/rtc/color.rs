@@ -1,16 +1,17 @@
+use crate::*;
 extern crate float_cmp;
-use self::float_cmp::{ApproxEq, F32Margin};
+use self::float_cmp::ApproxEq;
 use std::ops;
 
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub struct Color {
-    pub r: f32,
-    pub g: f32,
-    pub b: f32,
+    pub r: Float,
+    pub g: Float,
+    pub b: Float,
 }
 
 impl Color {
-    pub fn new(r: f32, g: f32, b: f32) -> Color {
+    pub fn new(r: Float, g: Float, b: Float) -> Color {
         Color { r, g, b }
     }
 
@@ -46,11 +47,11 @@ pub fn red() -> Color {
 pub fn white() -> Color {
     Color::new(1.0, 1.0, 1.0)
 }
-pub fn grey(x: f32) -> Color {
+pub fn grey(x: Float) -> Color {
     Color::new(x, x, x)
 }
 
-fn clamp(value: f32, min: f32, max: f32) -> f32 {
+fn clamp(value: Float, min: Float, max: Float) -> Float {
     if value < min {
         min
     } else if value > max {
@@ -62,7 +63,7 @@ fn clamp(value: f32, min: f32, max: f32) -> f32 {
 
 /// based on https://docs.rs/float-cmp/0.6.0/float_cmp/index.html
 impl ApproxEq for Color {
-    type Margin = F32Margin;
+    type Margin = FloatMargin;
 
     fn approx_eq<T: Into<Self::Margin>>(self, other: Self, margin: T) -> bool {
         let margin = margin.into();
@@ -81,7 +82,7 @@ impl_op_ex!(-|c1: &Color, c2: &Color| -> Color {
 impl_op_ex!(*|c1: &Color, c2: &Color| -> Color {
     Color::new(c1.r * c2.r, c1.g * c2.g, c1.b * c2.b)
 });
-impl_op_ex!(*|c1: &Color, x: f32| -> Color { Color::new(c1.r * x, c1.g * x, c1.b * x) });
+impl_op_ex!(*|c1: &Color, x: Float| -> Color { Color::new(c1.r * x, c1.g * x, c1.b * x) });
 
 impl ops::AddAssign<Color> for Color {
     fn add_assign(&mut self, c2: Color) {
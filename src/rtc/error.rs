@@ -0,0 +1,181 @@
+use crate::*;
+use std::error::Error;
+use std::fmt;
+
+// things which can be wrong with a scene, eg one read in from a scene file.
+//
+// Everything with a transformation (shapes, patterns, the camera, motions and
+// environment maps) has two constructors: `try_new`, which gives back one of these,
+// and `new`, which panics with it instead. `new` is for scenes built in code, where
+// a bad transformation is a bug; `try_new` is for input like scene files and
+// animations. The same goes for `try_set_transform` and `set_transform`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RtcError {
+    // a transformation which can't be inverted (eg one which scales something
+    // down to nothing), so rays can't be moved into the object's space.
+    // `object` describes what it belonged to, eg "Sphere" or "Stripe pattern".
+    // The matrix is boxed to keep the error (and every `Result` holding one) small.
+    SingularTransform {
+        object: String,
+        transform: Box<Matrix4>,
+    },
+    // a size which would make a shape impossible to hit, eg a negative radius
+    InvalidSize {
+        object: String,
+        message: String,
+    },
+    // something refers to an object (or light) by where it is in the world,
+    // eg "objects[3]", but the world doesn't have one there
+    MissingObject {
+        object: String,
+    },
+}
+
+impl RtcError {
+    pub fn singular_transform(object: &str, transform: Matrix4) -> RtcError {
+        RtcError::SingularTransform {
+            object: object.to_string(),
+            transform: Box::new(transform),
+        }
+    }
+
+    pub fn invalid_size(object: &str, message: String) -> RtcError {
+        RtcError::InvalidSize {
+            object: object.to_string(),
+            message,
+        }
+    }
+
+    pub fn missing_object(object: &str) -> RtcError {
+        RtcError::MissingObject {
+            object: object.to_string(),
+        }
+    }
+
+    // adds more detail about which object went wrong, eg the name it was given in a scene file
+    pub fn in_object(self, name: &str) -> RtcError {
+        match self {
+            RtcError::SingularTransform { object, transform } => RtcError::SingularTransform {
+                object: format!("{} '{}'", object, name),
+                transform,
+            },
+            RtcError::InvalidSize { object, message } => RtcError::InvalidSize {
+                object: format!("{} '{}'", object, name),
+                message,
+            },
+            RtcError::MissingObject { object } => RtcError::MissingObject {
+                object: format!("{} '{}'", object, name),
+            },
+        }
+    }
+}
+
+impl fmt::Display for RtcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RtcError::SingularTransform { object, transform } => {
+                let row = |r: usize| {
+                    let m = transform;
+                    format!(
+                        "[{}, {}, {}, {}]",
+                        m[(r, 0)],
+                        m[(r, 1)],
+                        m[(r, 2)],
+                        m[(r, 3)]
+                    )
+                };
+                write!(
+                    f,
+                    "{} has a transformation which can't be inverted: [{}, {}, {}, {}]",
+                    object,
+                    row(0),
+                    row(1),
+                    row(2),
+                    row(3)
+                )
+            }
+            RtcError::InvalidSize { object, message } => {
+                write!(f, "{} has an invalid size: {}", object, message)
+            }
+            RtcError::MissingObject { object } => write!(f, "{} isn't in the world", object),
+        }
+    }
+}
+
+impl Error for RtcError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn singular_transform_errors_say_which_object_was_wrong() {
+        let e = RtcError::singular_transform("Sphere", scaling(1., 0., 2.)).in_object("table leg");
+        assert_eq!(
+            "Sphere 'table leg' has a transformation which can't be inverted: \
+             [[1, 0, 0, 0], [0, 0, 0, 0], [0, 0, 2, 0], [0, 0, 0, 1]]",
+            e.to_string()
+        );
+    }
+
+    #[test]
+    fn everything_with_a_transformation_rejects_singular_ones() {
+        let flat = scaling(1., 0., 1.);
+        let m = Material::default();
+        let image = Framebuffer::new(2, 1);
+        let errors = vec![
+            ("Sphere", Sphere::try_new(flat, m.clone()).err()),
+            ("Plane", Plane::try_new(flat, m.clone()).err()),
+            ("Disk", Disk::try_new(flat, m.clone(), 1.).err()),
+            (
+                "Rectangle",
+                Rectangle::try_new(flat, m.clone(), 1., 1.).err(),
+            ),
+            (
+                "Motion start",
+                Motion::try_new(flat, Matrix4::identity()).err(),
+            ),
+            (
+                "Motion end",
+                Motion::try_new(Matrix4::identity(), flat).err(),
+            ),
+            (
+                "Stripe pattern",
+                Stripe::try_new(white(), black(), flat).err(),
+            ),
+            (
+                "Gradient pattern",
+                Gradient::try_new(white(), black(), flat).err(),
+            ),
+            ("Ring pattern", Ring::try_new(white(), black(), flat).err()),
+            (
+                "Checkerboard pattern",
+                Checkerboard::try_new(solid(white()), solid(black()), flat).err(),
+            ),
+            (
+                "SphereMap pattern",
+                SphereMap::try_new(solid(white()), flat).err(),
+            ),
+            ("Camera", Camera::try_new(10, 10, 1., flat).err()),
+            ("EnvironmentMap", EnvironmentMap::try_new(image, flat).err()),
+        ];
+        for (object, error) in errors {
+            assert_eq!(Some(RtcError::singular_transform(object, flat)), error);
+        }
+    }
+
+    #[test]
+    fn failed_updates_leave_things_where_they_were() {
+        let flat = scaling(1., 0., 1.);
+
+        let mut s = Sphere::pos_r(point(1., 2., 3.), 2.);
+        assert!(s.try_set_transform(flat).is_err());
+        assert_eq!(Sphere::pos_r(point(1., 2., 3.), 2.), s);
+
+        let mut c = Camera::from_size(10, 10, 1.);
+        assert!(c.try_set_view_transform(flat).is_err());
+        assert!(c.try_set_end_view_transform(Some(flat)).is_err());
+        assert_eq!(Matrix4::identity(), c.view_transform());
+        assert_eq!(None, c.end_view_transform());
+    }
+}
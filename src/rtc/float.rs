@@ -0,0 +1,34 @@
+extern crate float_cmp;
+
+// all of the maths uses this type, which is f32 by default. Building with
+// `--features f64` switches everything over to f64 instead, for scenes which
+// run out of precision with f32 (eg shadow acne on large or distant objects)
+#[cfg(not(feature = "f64"))]
+pub type Float = f32;
+#[cfg(feature = "f64")]
+pub type Float = f64;
+
+// constants like PI for whichever precision we're using
+#[cfg(not(feature = "f64"))]
+pub use std::f32::consts;
+#[cfg(feature = "f64")]
+pub use std::f64::consts;
+
+// for file formats which always store f32 values
+#[allow(clippy::unnecessary_cast)] // only unnecessary when Float is already f32
+pub fn to_f32(x: Float) -> f32 {
+    x as f32
+}
+
+#[cfg(not(feature = "f64"))]
+pub type FloatMargin = self::float_cmp::F32Margin;
+#[cfg(feature = "f64")]
+pub type FloatMargin = self::float_cmp::F64Margin;
+
+// how far points are moved off of surfaces before casting rays from them, so that
+// the rays don't hit the surface they started from. Smaller values cause lots of
+// artifacts with f32, but f64 is precise enough to get away with a much smaller one.
+#[cfg(not(feature = "f64"))]
+pub const EPSILON: Float = 0.0001;
+#[cfg(feature = "f64")]
+pub const EPSILON: Float = 0.000_000_01;
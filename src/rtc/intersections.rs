@@ -4,13 +4,18 @@ mod ray_world;
 pub use self::ray_world::*;
 mod ray_plane;
 pub use self::ray_plane::*;
+mod ray_disk;
+mod ray_packet;
+mod ray_rectangle;
+pub use self::ray_packet::*;
 
 use crate::*;
 use std::cmp::Ordering::Equal;
+use std::ops::Deref;
 
 #[derive(Debug, Clone, Copy)]
 pub struct Intersection<'a> {
-    pub t: f32,
+    pub t: Float,
     pub obj: &'a dyn Shape,
 }
 
@@ -19,25 +24,104 @@ impl Intersection<'_> {
         vec![]
     }
 
-    pub fn ray_sphere(sphere: &Sphere, t: f32) -> Intersection {
+    pub fn ray_sphere(sphere: &Sphere, t: Float) -> Intersection {
         Intersection { t, obj: sphere }
     }
 
-    pub fn ray_plane(plane: &Plane, t: f32) -> Intersection {
+    pub fn ray_plane(plane: &Plane, t: Float) -> Intersection {
         Intersection { t, obj: plane }
     }
 
-    pub fn hit<'a>(intersections: &'a [Intersection<'a>]) -> Option<&'a Intersection<'a>> {
-        let mut sorted = intersections
-            .into_iter()
+    // the hit borrows the shape it's on rather than the list, so it can outlive the list
+    pub fn hit<'a>(intersections: &[Intersection<'a>]) -> Option<Intersection<'a>> {
+        // we want the element with the smallest non-negative t value (the first one,
+        // if there's a tie). This doesn't need sorting, so it doesn't allocate anything.
+        // NaN values fail the `>= 0.` check, so they never count as hits.
+        intersections
+            .iter()
             .filter(|a| a.t >= 0.)
-            .collect::<Vec<_>>();
+            .fold(None, |best: Option<&Intersection>, i| match best {
+                Some(b) if b.t <= i.t => Some(b),
+                _ => Some(i),
+            })
+            .copied()
+    }
+}
+
+// every intersection along a ray, sorted by t (see `World::intersect`)
+#[derive(Debug, Clone)]
+pub struct Hits<'a> {
+    intersections: Vec<Intersection<'a>>,
+}
+
+impl<'a> Hits<'a> {
+    pub fn new(mut intersections: Vec<Intersection<'a>>) -> Hits<'a> {
+        // a stable sort, so that ties stay in the order of `World::objects`
+        intersections.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap_or(Equal));
+        Hits { intersections }
+    }
+
+    // the closest intersection in front of the ray's origin
+    pub fn hit(&self) -> Option<Intersection<'a>> {
+        Intersection::hit(&self.intersections)
+    }
+}
+
+impl<'a> Deref for Hits<'a> {
+    type Target = [Intersection<'a>];
+
+    fn deref(&self) -> &[Intersection<'a>] {
+        &self.intersections
+    }
+}
+
+// the t values where a ray hits a shape, in object space. These are kept on the
+// stack rather than in a Vec, since there are only ever a few of them for each shape.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LocalHits {
+    t: [Float; LocalHits::MAX],
+    len: usize,
+}
+
+impl LocalHits {
+    // the most times any one of our shapes can be hit by a ray
+    pub const MAX: usize = 4;
+
+    pub fn none() -> LocalHits {
+        LocalHits {
+            t: [0.; LocalHits::MAX],
+            len: 0,
+        }
+    }
+
+    pub fn one(t: Float) -> LocalHits {
+        let mut hits = LocalHits::none();
+        hits.push(t);
+        hits
+    }
+
+    pub fn two(t1: Float, t2: Float) -> LocalHits {
+        let mut hits = LocalHits::one(t1);
+        hits.push(t2);
+        hits
+    }
+
+    pub fn push(&mut self, t: Float) {
+        assert!(self.len < LocalHits::MAX, "too many hits for one shape");
+        self.t[self.len] = t;
+        self.len += 1;
+    }
+
+    pub fn as_slice(&self) -> &[Float] {
+        &self.t[..self.len]
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
 
-        // f32 doesn't implement Ord because NaN values mean there isn't a total ordering
-        // we have to decide how to cope with that ourselves:
-        sorted.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap_or(Equal));
-        // we want the first element with a positive t value
-        sorted.into_iter().next()
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
     }
 }
 
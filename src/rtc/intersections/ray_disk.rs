@@ -0,0 +1,81 @@
+use crate::*;
+
+impl LocalRayIntersection for Disk {
+    fn local_intersections(&self, ray: Ray) -> LocalHits {
+        if approx_eq!(Float, ray.direction.y, 0.) {
+            // parallel or coplanar with the disk, which counts as a miss like it does for planes
+            return LocalHits::none();
+        }
+
+        // where the ray crosses the xz plane (see `Plane::local_intersections`),
+        // which only counts if it's between the inner and outer edges
+        let t = -ray.origin.y / ray.direction.y;
+        let p = ray.position(t);
+        let distance_squared = p.x * p.x + p.z * p.z;
+        let (outer, inner) = (self.radius(), self.inner_radius());
+        if distance_squared > outer * outer || distance_squared < inner * inner {
+            return LocalHits::none();
+        }
+
+        LocalHits::one(t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rays_hit_disks_inside_their_radius() {
+        let d = Disk::new(Matrix4::identity(), Default::default(), 2.);
+        let down = vec(0., -1., 0.);
+
+        let t = |x: Float, z: Float| d.ray_intersection(Ray::new(point(x, 3., z), down));
+        assert_eq!(3., t(0., 0.)[0].t);
+        assert_eq!(3., t(1.2, -1.5)[0].t);
+        // the edge counts as part of the disk
+        assert_eq!(1, t(2., 0.).len());
+        assert_eq!(0, t(1.5, 1.5).len());
+        assert_eq!(0, t(0., -2.1).len());
+
+        // rays along the disk miss it
+        let r = Ray::new(point(-5., 0., 0.), vec(1., 0., 0.));
+        assert_eq!(0, d.ray_intersection(r).len());
+    }
+
+    #[test]
+    fn rays_go_through_the_hole_in_an_annulus() {
+        let d = Disk::unit().with_inner_radius(0.5);
+        let down = vec(0., -1., 0.);
+
+        let hits = |x: Float| d.ray_intersection(Ray::new(point(x, 1., 0.), down)).len();
+        assert_eq!(0, hits(0.));
+        assert_eq!(0, hits(0.49));
+        assert_eq!(1, hits(0.5));
+        assert_eq!(1, hits(0.75));
+        assert_eq!(0, hits(1.01));
+    }
+
+    #[test]
+    fn rays_hit_transformed_disks() {
+        // a disk standing up, facing -z, like a target
+        let d = Disk::new(
+            translation(0., 1., 5.) * rotation_x(-crate::consts::PI / 2.),
+            Default::default(),
+            1.,
+        );
+
+        let r = Ray::new(point(0.5, 1.5, 0.), vec(0., 0., 1.));
+        let i = d.ray_intersection(r);
+        assert_eq!(1, i.len());
+        assert!(approx_eq!(Float, 5., i[0].t, epsilon = 0.00001));
+        assert_tuple_eq!(
+            Normal3::new(0., 0., -1.),
+            d.normal_at(r.position(i[0].t)),
+            epsilon = 0.00001
+        );
+
+        let r = Ray::new(point(0., 2.5, 0.), vec(0., 0., 1.));
+        assert_eq!(0, d.ray_intersection(r).len());
+    }
+}
@@ -0,0 +1,265 @@
+use crate::*;
+use std::ops::Mul;
+
+// four rays traced together. Neighbouring primary rays from the camera take very
+// similar paths, so they can share the work of intersecting each shape.
+#[derive(Debug, Clone, Copy)]
+pub struct RayPacket {
+    pub origin: Point3x4,
+    pub direction: Vector3x4,
+    // all of the rays are cast at the same time (see `Ray::time`)
+    pub time: Float,
+}
+
+impl RayPacket {
+    pub fn new(rays: [Ray; 4]) -> RayPacket {
+        debug_assert!(rays.iter().all(|r| r.time == rays[0].time));
+
+        RayPacket {
+            origin: Point3x4::new([
+                rays[0].origin,
+                rays[1].origin,
+                rays[2].origin,
+                rays[3].origin,
+            ]),
+            direction: Vector3x4::new([
+                rays[0].direction,
+                rays[1].direction,
+                rays[2].direction,
+                rays[3].direction,
+            ]),
+            time: rays[0].time,
+        }
+    }
+
+    pub fn ray(&self, i: usize) -> Ray {
+        Ray::new(self.origin.lane(i), self.direction.lane(i)).with_time(self.time)
+    }
+}
+
+impl Mul<RayPacket> for Matrix4 {
+    type Output = RayPacket;
+
+    fn mul(self, other: RayPacket) -> RayPacket {
+        RayPacket {
+            origin: self * other.origin,
+            direction: self * other.direction,
+            time: other.time,
+        }
+    }
+}
+
+// intersect four rays with an object in world space, giving the closest hit for each
+pub trait PacketIntersection {
+    fn packet_intersection(&self, packet: &RayPacket) -> [Option<Intersection<'_>>; 4];
+}
+
+// intersect four rays with an object in object space. Gives the smallest non-negative
+// t value for each ray, or infinity where a ray misses.
+pub trait LocalPacketIntersection: LocalRayIntersection {
+    // shapes without a SIMD version just intersect each ray separately
+    fn local_packet_intersection(&self, packet: &RayPacket) -> Float4 {
+        let t = |i: usize| {
+            self.local_intersections(packet.ray(i))
+                .as_slice()
+                .iter()
+                .filter(|t| **t >= 0.)
+                .fold(Float::INFINITY, |a, b| a.min(*b))
+        };
+        Float4::new(t(0), t(1), t(2), t(3))
+    }
+}
+
+impl<T: LocalPacketIntersection + Shape> PacketIntersection for T {
+    fn packet_intersection(&self, packet: &RayPacket) -> [Option<Intersection<'_>>; 4] {
+        let local_packet = self.world_to_object_at_time(packet.time) * *packet;
+
+        let in_bounds = self.local_bounds().intersects_packet(&local_packet);
+        if !in_bounds.any() {
+            return [None; 4];
+        }
+
+        let t = Float4::select(
+            in_bounds,
+            self.local_packet_intersection(&local_packet),
+            Float4::splat(Float::INFINITY),
+        )
+        .to_array();
+        let hit = |i: usize| {
+            if t[i] < Float::INFINITY {
+                Some(Intersection { t: t[i], obj: self })
+            } else {
+                None
+            }
+        };
+        [hit(0), hit(1), hit(2), hit(3)]
+    }
+}
+
+impl LocalPacketIntersection for Sphere {
+    // the same quadratic as `Sphere::local_intersections`
+    fn local_packet_intersection(&self, packet: &RayPacket) -> Float4 {
+        let sphere_to_ray = packet.origin - Point3x4::splat(Point3::origin());
+        let dir = packet.direction;
+        let a = dir.dot(&dir);
+        let b = Float4::splat(2.) * dir.dot(&sphere_to_ray);
+        let c = sphere_to_ray.dot(&sphere_to_ray) - Float4::splat(1.);
+
+        let zero = Float4::splat(0.);
+        let discriminant = b * b - Float4::splat(4.) * a * c;
+        let root = discriminant.max(zero).sqrt();
+        let t1 = (zero - b - root) / (Float4::splat(2.) * a);
+        let t2 = (zero - b + root) / (Float4::splat(2.) * a);
+
+        // t1 <= t2, so take t1 unless it's behind the ray's origin
+        let infinity = Float4::splat(Float::INFINITY);
+        let nearest = Float4::select(t1.ge(zero), t1, Float4::select(t2.ge(zero), t2, infinity));
+        Float4::select(discriminant.ge(zero), nearest, infinity)
+    }
+}
+
+impl LocalPacketIntersection for Plane {
+    // the same as `Plane::local_intersections`
+    fn local_packet_intersection(&self, packet: &RayPacket) -> Float4 {
+        let zero = Float4::splat(0.);
+        let t = (zero - packet.origin.y) / packet.direction.y;
+
+        // rays parallel to the plane miss it
+        let parallel = packet.direction.y.abs().le(Float4::splat(Float::EPSILON));
+        Float4::select(!parallel & t.ge(zero), t, Float4::splat(Float::INFINITY))
+    }
+}
+
+// flat shapes are cheap to test one ray at a time, and the bounding box check
+// already skips most rays which miss them
+impl LocalPacketIntersection for Disk {}
+
+impl LocalPacketIntersection for Rectangle {}
+
+impl World {
+    // the closest hit for each of the rays, like `World::closest_hit`
+    pub fn packet_hits(&self, packet: &RayPacket) -> [Option<Intersection<'_>>; 4] {
+        let mut result: [Option<Intersection>; 4] = [None; 4];
+
+        for obj in &self.objects {
+            let hits = obj.packet_intersection(packet);
+            for (closest, hit) in result.iter_mut().zip(hits.iter()) {
+                match (*closest, *hit) {
+                    (Some(c), Some(h)) if h.t < c.t => *closest = Some(h),
+                    (None, Some(h)) => *closest = Some(h),
+                    _ => {}
+                }
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rays() -> [Ray; 4] {
+        [
+            Ray::new(point(0., 0., -5.), vec(0., 0., 1.)),
+            Ray::new(point(0., 0.5, -5.), vec(0., 0., 1.)),
+            Ray::new(point(0., 2., -5.), vec(0., 0., 1.)),
+            Ray::new(point(0., 3., 0.), vec(0.2, -1., 0.4).normalize()),
+        ]
+    }
+
+    // the closest non-negative t for each ray, using the one-ray-at-a-time path
+    fn scalar_hits<T: RayIntersection>(shape: &T, rays: [Ray; 4]) -> Vec<Option<Float>> {
+        rays.iter()
+            .map(|r| Intersection::hit(&shape.ray_intersection(*r)).map(|i| i.t))
+            .collect()
+    }
+
+    fn packet_hits<T: PacketIntersection>(shape: &T, rays: [Ray; 4]) -> Vec<Option<Float>> {
+        shape
+            .packet_intersection(&RayPacket::new(rays))
+            .iter()
+            .map(|i| i.map(|i| i.t))
+            .collect()
+    }
+
+    fn assert_hits_eq(expected: Vec<Option<Float>>, actual: Vec<Option<Float>>) {
+        assert_eq!(expected.len(), actual.len());
+        for (e, a) in expected.into_iter().zip(actual) {
+            match (e, a) {
+                (Some(e), Some(a)) => assert!(approx_eq!(Float, e, a, epsilon = 0.0001)),
+                (e, a) => assert_eq!(e, a),
+            }
+        }
+    }
+
+    #[test]
+    fn transforming_a_packet_transforms_each_ray() {
+        let m = translation(3., 4., 5.) * scaling(2., 3., 4.);
+        let packet = m * RayPacket::new(rays());
+        for (i, r) in rays().iter().enumerate() {
+            assert_ray_eq!(m * *r, packet.ray(i), epsilon = 0.00001);
+        }
+    }
+
+    #[test]
+    fn packets_hit_spheres_like_single_rays() {
+        let s = Sphere::unit();
+        assert_hits_eq(scalar_hits(&s, rays()), packet_hits(&s, rays()));
+        let small = Sphere::pos_r(point(0., 0., 0.), 0.4);
+        let hit_or_miss = packet_hits(&small, rays())
+            .iter()
+            .map(|t| t.is_some())
+            .collect::<Vec<_>>();
+        assert_eq!(vec![true, false, false, false], hit_or_miss);
+
+        let s = Sphere::pos_r(point(0., 1., 0.), 2.);
+        assert_hits_eq(scalar_hits(&s, rays()), packet_hits(&s, rays()));
+
+        // rays starting inside the sphere hit its far side
+        let inside = [Ray::new(point(0., 0., 0.), vec(0., 0., 1.)); 4];
+        assert_eq!(vec![Some(1.); 4], packet_hits(&Sphere::unit(), inside));
+    }
+
+    #[test]
+    fn packets_hit_planes_like_single_rays() {
+        let p = Plane::xz();
+        assert_hits_eq(scalar_hits(&p, rays()), packet_hits(&p, rays()));
+
+        let mut p = Plane::xz();
+        p.set_transform(translation(0., 1., 0.) * rotation_x(0.5));
+        assert_hits_eq(scalar_hits(&p, rays()), packet_hits(&p, rays()));
+    }
+
+    #[test]
+    fn packets_hit_flat_shapes_like_single_rays() {
+        let d = Disk::new(rotation_x(-0.3), Default::default(), 1.).with_inner_radius(0.25);
+        assert_hits_eq(scalar_hits(&d, rays()), packet_hits(&d, rays()));
+
+        let r = Rectangle::new(translation(0., 0.5, 1.), Default::default(), 1.2, 3.);
+        assert_hits_eq(scalar_hits(&r, rays()), packet_hits(&r, rays()));
+        let hit_or_miss = packet_hits(&r, rays())
+            .iter()
+            .map(|t| t.is_some())
+            .collect::<Vec<_>>();
+        assert_eq!(vec![false, false, false, true], hit_or_miss);
+    }
+
+    #[test]
+    fn packets_find_the_closest_hit_in_the_world() {
+        let w = World::default();
+        let hits = w.packet_hits(&RayPacket::new(rays()));
+
+        for (r, hit) in rays().iter().zip(hits.iter()) {
+            let expected = w.intersect(*r).hit();
+            assert_eq!(expected.map(|i| i.t), hit.map(|i| i.t));
+            if let (Some(e), Some(h)) = (expected, hit) {
+                assert!(std::ptr::eq(
+                    e.obj as *const dyn Shape as *const (),
+                    h.obj as *const dyn Shape as *const ()
+                ));
+            }
+        }
+    }
+}
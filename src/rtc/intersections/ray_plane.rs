@@ -1,19 +1,16 @@
 use crate::*;
 
 impl LocalRayIntersection for Plane {
-    fn local_ray_intersection(&self, ray: Ray) -> Vec<Intersection> {
-        if approx_eq!(f32, ray.direction.y, 0.) {
+    fn local_intersections(&self, ray: Ray) -> LocalHits {
+        if approx_eq!(Float, ray.direction.y, 0.) {
             // ray doesn't move in y axis, so it's parallel or coplanar with the xz plane
-            return Intersection::none();
+            return LocalHits::none();
         }
 
         // we need to find the t value for the point in the ray where y=0.
         // the ray equation is y = origin_y + direction_y * t
         // therefore we calculate t = -origin_y / direction_y
-        vec![Intersection::ray_plane(
-            self,
-            -ray.origin.y / ray.direction.y,
-        )]
+        LocalHits::one(-ray.origin.y / ray.direction.y)
     }
 }
 
@@ -45,8 +42,8 @@ mod tests {
     fn ray_hitting_plane_from_above_hits() {
         let r = Ray::new(point(-1., 1., -1.), vec(1., -1., 1.).normalize());
         let p = Plane::xz();
-        let s33 = 3_f32.sqrt();
+        let s33 = Float::sqrt(3.);
         let intersection = p.ray_intersection(r)[0];
-        assert!(approx_eq!(f32, s33, intersection.t));
+        assert!(approx_eq!(Float, s33, intersection.t));
     }
 }
@@ -0,0 +1,64 @@
+use crate::*;
+
+impl LocalRayIntersection for Rectangle {
+    fn local_intersections(&self, ray: Ray) -> LocalHits {
+        if approx_eq!(Float, ray.direction.y, 0.) {
+            // parallel or coplanar with the rectangle, which counts as a miss like it does for planes
+            return LocalHits::none();
+        }
+
+        // where the ray crosses the xz plane (see `Plane::local_intersections`),
+        // which only counts if it's inside the rectangle's edges
+        let t = -ray.origin.y / ray.direction.y;
+        let p = ray.position(t);
+        if p.x.abs() > self.width() / 2. || p.z.abs() > self.depth() / 2. {
+            return LocalHits::none();
+        }
+
+        LocalHits::one(t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rays_hit_rectangles_inside_their_edges() {
+        let r = Rectangle::new(Matrix4::identity(), Default::default(), 4., 2.);
+        let down = vec(0., -1., 0.);
+
+        let t = |x: Float, z: Float| r.ray_intersection(Ray::new(point(x, 2., z), down));
+        assert_eq!(2., t(0., 0.)[0].t);
+        assert_eq!(2., t(-1.9, 0.9)[0].t);
+        // the edges and corners count as part of the rectangle
+        assert_eq!(1, t(2., -1.).len());
+        assert_eq!(0, t(2.1, 0.).len());
+        assert_eq!(0, t(0., 1.1).len());
+
+        // coming up from underneath works too
+        let i = r.ray_intersection(Ray::new(point(1., -3., 0.), vec(0., 1., 0.)));
+        assert_eq!(3., i[0].t);
+
+        // rays along the rectangle miss it
+        let along = Ray::new(point(-5., 0., 0.), vec(1., 0., 0.));
+        assert_eq!(0, r.ray_intersection(along).len());
+    }
+
+    #[test]
+    fn rays_hit_transformed_rectangles() {
+        // a 2x2 square, scaled to a table top 3 wide and 1 deep at y = 1
+        let r = Rectangle::new(
+            translation(0., 1., 0.) * scaling(1.5, 1., 0.5),
+            Default::default(),
+            2.,
+            2.,
+        );
+        let down = vec(0., -1., 0.);
+
+        let hits = |x: Float, z: Float| r.ray_intersection(Ray::new(point(x, 5., z), down));
+        assert_eq!(4., hits(1.4, 0.4)[0].t);
+        assert_eq!(0, hits(1.6, 0.).len());
+        assert_eq!(0, hits(0., 0.6).len());
+    }
+}
@@ -1,7 +1,7 @@
 use crate::*;
 
 impl LocalRayIntersection for Sphere {
-    fn local_ray_intersection(&self, ray: Ray) -> Vec<Intersection> {
+    fn local_intersections(&self, ray: Ray) -> LocalHits {
         let sphere_to_ray = ray.origin - point(0., 0., 0.);
         let dir = ray.direction;
         let a = dir.dot(dir);
@@ -11,16 +11,13 @@ impl LocalRayIntersection for Sphere {
         let discriminant = b.powf(2.) - 4. * a * c;
 
         if discriminant < 0. {
-            return Intersection::none();
+            return LocalHits::none();
         }
 
         let t1 = (-b - discriminant.sqrt()) / (2. * a);
         let t2 = (-b + discriminant.sqrt()) / (2. * a);
 
-        vec![
-            Intersection::ray_sphere(&self, t1),
-            Intersection::ray_sphere(&self, t2),
-        ]
+        LocalHits::two(t1, t2)
     }
 }
 
@@ -65,4 +62,19 @@ mod tests {
         let i2 = s2.ray_intersection(r2);
         assert_eq!(0, i2.len());
     }
+
+    #[test]
+    fn local_hits_are_kept_in_order_without_allocating() {
+        let hits =
+            Sphere::unit().local_intersections(Ray::new(point(0., 0., -5.), vec(0., 0., 1.)));
+        assert_eq!(&[4., 6.], hits.as_slice());
+        assert_eq!(2, hits.len());
+
+        let mut hits = LocalHits::none();
+        assert!(hits.is_empty());
+        for t in &[3., 1., 2., 4.] {
+            hits.push(*t);
+        }
+        assert_eq!(&[3., 1., 2., 4.], hits.as_slice());
+    }
 }
@@ -1,17 +1,49 @@
 use crate::*;
 
-impl RayIntersection for &World {
-    fn ray_intersection(&self, ray: Ray) -> Vec<Intersection> {
-        let mut result = Vec::<Intersection>::new();
+// the intersections all borrow from the world, so they live as long as it does
+impl World {
+    // every intersection along the ray, sorted by t
+    pub fn intersect(&self, ray: Ray) -> Hits<'_> {
+        let mut intersections = vec![];
+        for obj in &self.objects {
+            obj.intersect_into(ray, &mut intersections);
+        }
+
+        Hits::new(intersections)
+    }
+
+    // like `intersect`, but adds the intersections onto the end of a reusable buffer
+    pub fn intersect_into<'a>(&'a self, ray: Ray, hits: &mut Vec<Intersection<'a>>) {
+        let start = hits.len();
 
         for obj in &self.objects {
-            let intersection = obj.ray_intersection(ray);
-            result.extend_from_slice(&intersection);
+            obj.intersect_into(ray, hits);
         }
 
-        result.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap_or(std::cmp::Ordering::Equal));
+        // only the intersections we added get sorted. This is stable like `Hits::new`,
+        // and for the handful of hits a ray usually has it doesn't allocate either.
+        hits[start..].sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap_or(std::cmp::Ordering::Equal));
+    }
+
+    // the intersection with the smallest t value in [tmin, tmax], if there is one.
+    // Like everywhere else, when two objects are hit at the same t the first one wins.
+    pub fn closest_hit(&self, ray: Ray, tmin: Float, tmax: Float) -> Option<Intersection<'_>> {
+        let mut closest: Option<Intersection> = None;
+        let mut tmax = tmax;
 
-        result
+        // once something has been hit, anything further away can be skipped
+        for obj in &self.objects {
+            match (closest, obj.closest_hit(ray, tmin, tmax)) {
+                (Some(c), Some(hit)) if c.t <= hit.t => {}
+                (_, Some(hit)) => {
+                    tmax = hit.t;
+                    closest = Some(hit);
+                }
+                _ => {}
+            }
+        }
+
+        closest
     }
 }
 
@@ -20,9 +52,9 @@ mod tests {
     use super::*;
     #[test]
     fn intersection_with_default_world() {
-        let w = &World::default();
+        let w = World::default();
         let r = Ray::new(point(0., 0., -5.), vec(0., 0., 1.));
-        let intersections = w.ray_intersection(r);
+        let intersections = w.intersect(r);
 
         assert_eq!(4, intersections.len());
 
@@ -31,5 +63,89 @@ mod tests {
         assert_eq!(4.5, intersections[1].t);
         assert_eq!(5.5, intersections[2].t);
         assert_eq!(6., intersections[3].t);
+        assert_eq!(4., intersections.hit().unwrap().t);
+    }
+
+    // the hit only borrows the world, not the list of intersections it came from
+    fn first_hit(world: &World, ray: Ray) -> Option<Intersection<'_>> {
+        world.intersect(ray).hit()
+    }
+
+    #[test]
+    fn hits_outlive_the_intersection_list() {
+        let w = World::default();
+        let r = Ray::new(point(0., 0., 0.), vec(0., 0., 1.));
+        let hit = first_hit(&w, r).unwrap();
+        assert_eq!(0.5, hit.t);
+        assert!(std::ptr::eq(
+            hit.obj as *const dyn Shape as *const (),
+            &*w.objects[1] as *const dyn IntersectableShape as *const ()
+        ));
+    }
+
+    #[test]
+    fn reusing_a_buffer_for_intersections() {
+        let w = World::default();
+        let r = Ray::new(point(0., 0., -5.), vec(0., 0., 1.));
+        let mut hits = Vec::with_capacity(8);
+
+        w.intersect_into(r, &mut hits);
+        let ts = hits.iter().map(|i| i.t).collect::<Vec<_>>();
+        assert_eq!(vec![4., 4.5, 5.5, 6.], ts);
+
+        // clearing keeps the buffer, so the next ray doesn't need to allocate
+        let capacity = hits.capacity();
+        hits.clear();
+        w.intersect_into(r.with_time(0.5), &mut hits);
+        assert_eq!(4, hits.len());
+        assert_eq!(capacity, hits.capacity());
+    }
+
+    #[test]
+    fn closest_hit_within_a_range() {
+        let w = World::default();
+        let r = Ray::new(point(0., 0., -5.), vec(0., 0., 1.));
+        let t = |tmin: Float, tmax: Float| w.closest_hit(r, tmin, tmax).map(|i| i.t);
+
+        assert_eq!(Some(4.), t(0., Float::INFINITY));
+        // the first hit can be skipped by starting further along the ray
+        assert_eq!(Some(4.5), t(4.1, Float::INFINITY));
+        // both ends of the range count
+        assert_eq!(Some(5.5), t(5.5, 6.));
+        assert_eq!(Some(6.), t(5.9, 6.));
+        // nothing in between the spheres' surfaces
+        assert_eq!(None, t(4.6, 5.4));
+        assert_eq!(None, t(0., 3.9));
+
+        // the same hit as `Hits::hit`, on the same object
+        let r = Ray::new(point(0., 0., 0.), vec(0., 1., 0.));
+        let expected = w.intersect(r).hit().unwrap();
+        let closest = w.closest_hit(r, 0., Float::INFINITY).unwrap();
+        assert_eq!(expected.t, closest.t);
+        assert!(std::ptr::eq(
+            expected.obj as *const dyn Shape as *const (),
+            closest.obj as *const dyn Shape as *const ()
+        ));
+    }
+
+    #[test]
+    fn ties_go_to_the_first_object_everywhere() {
+        // two planes in the same place, which every ray hits at exactly the same t
+        let w = World::new(vec![Box::new(Plane::xz()), Box::new(Plane::xz())], vec![]);
+        let first = &*w.objects[0] as *const dyn IntersectableShape as *const ();
+        let is_first =
+            |i: Intersection| std::ptr::eq(i.obj as *const dyn Shape as *const (), first);
+
+        let r = Ray::new(point(0., 1., 0.), vec(0., -1., 0.));
+        assert!(is_first(w.closest_hit(r, 0., Float::INFINITY).unwrap()));
+        assert!(is_first(w.intersect(r).hit().unwrap()));
+        assert!(is_first(w.intersect(r)[0]));
+
+        let mut hits = vec![];
+        w.intersect_into(r, &mut hits);
+        assert!(is_first(hits[0]));
+
+        let packet = w.packet_hits(&RayPacket::new([r; 4]));
+        assert!(packet.iter().all(|h| is_first(h.unwrap())));
     }
 }
@@ -1,36 +1,75 @@
 use crate::*;
 
+mod integrator;
+pub use self::integrator::*;
+mod path_tracing;
+pub use self::path_tracing::*;
+mod ambient_occlusion;
+pub use self::ambient_occlusion::*;
+mod aov;
+pub use self::aov::*;
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct PointLight {
     intensity: Color,
-    position: Tuple,
+    position: Point3,
 }
 
 impl PointLight {
-    pub fn new(intensity: Color, position: Tuple) -> PointLight {
+    pub fn new(intensity: Color, position: Point3) -> PointLight {
         PointLight {
             intensity,
             position,
         }
     }
+
+    pub fn intensity(&self) -> Color {
+        self.intensity
+    }
+
+    pub fn position(&self) -> Point3 {
+        self.position
+    }
+}
+
+// how much light a surface reflects towards the eye. Implemented by each
+// material type so that they can all share the same light/shadow plumbing.
+pub trait LightingModel {
+    // light given off by the surface itself
+    fn emissive(&self, object_point: Point3) -> Color;
+    // a constant approximation of light that reaches the surface indirectly
+    fn ambient(&self, light: PointLight, object_point: Point3) -> Color;
+    // light reflected towards the eye directly from `light`, ignoring any shadows
+    fn direct(
+        &self,
+        light: PointLight,
+        world_point: Point3,
+        object_point: Point3,
+        eye: Vector3,
+        surface_normal: Normal3,
+    ) -> Color;
+    // the proportion of light reflected diffusely, used when tracing indirect light bounces
+    fn albedo(&self, object_point: Point3) -> Color;
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct PhongMaterial {
     pub pattern: Pattern,
-    pub ambient: f32,
-    pub diffuse: f32,
-    pub specular: f32,
-    pub shininess: f32,
+    pub ambient: Float,
+    pub diffuse: Float,
+    pub specular: Float,
+    pub shininess: Float,
+    // light given off by the surface itself, regardless of any lights in the scene
+    pub emissive: Pattern,
 }
 
 impl PhongMaterial {
     pub fn solid(
         color: Color,
-        ambient: f32,
-        diffuse: f32,
-        specular: f32,
-        shininess: f32,
+        ambient: Float,
+        diffuse: Float,
+        specular: Float,
+        shininess: Float,
     ) -> PhongMaterial {
         PhongMaterial {
             pattern: solid(color),
@@ -38,6 +77,7 @@ impl PhongMaterial {
             diffuse,
             specular,
             shininess,
+            emissive: solid(black()),
         }
     }
 }
@@ -48,15 +88,130 @@ impl Default for PhongMaterial {
     }
 }
 
+impl LightingModel for PhongMaterial {
+    fn emissive(&self, object_point: Point3) -> Color {
+        self.emissive.sample_pattern_at(object_point)
+    }
+
+    fn ambient(&self, light: PointLight, object_point: Point3) -> Color {
+        let color = self.pattern.sample_pattern_at(object_point);
+        color * light.intensity * self.ambient
+    }
+
+    fn direct(
+        &self,
+        light: PointLight,
+        world_point: Point3,
+        object_point: Point3,
+        eye: Vector3,
+        surface_normal: Normal3,
+    ) -> Color {
+        let color = self.pattern.sample_pattern_at(object_point);
+        let effective_color = color * light.intensity;
+        let light_direction = (light.position - world_point).normalize();
+
+        let cos_light_angle = light_direction.dot(surface_normal);
+        let diffuse = match cos_light_angle {
+            x if x < 0. => black(),                  // light is behind surface normal
+            x => effective_color * self.diffuse * x, // light is in front, modified by angle
+        };
+        let specular = match cos_light_angle {
+            x if x < 0. => black(),
+            _ => {
+                let cos_reflection_angle = reflect(-light_direction, surface_normal).dot(eye);
+                match cos_reflection_angle {
+                    x if x < 0. => black(),
+                    x => {
+                        let factor = x.powf(self.shininess);
+                        light.intensity * self.specular * factor
+                    }
+                }
+            }
+        };
+
+        diffuse + specular
+    }
+
+    fn albedo(&self, object_point: Point3) -> Color {
+        self.pattern.sample_pattern_at(object_point) * self.diffuse
+    }
+}
+
+// the same enum/impl trick as `Pattern`, so that each object can pick its own lighting model
+#[derive(Debug, Clone, PartialEq)]
+pub enum Material {
+    Phong(PhongMaterial),
+    Pbr(PbrMaterial),
+}
+impl LightingModel for Material {
+    fn emissive(&self, object_point: Point3) -> Color {
+        match self {
+            Material::Phong(m) => m.emissive(object_point),
+            Material::Pbr(m) => m.emissive(object_point),
+        }
+    }
+
+    fn ambient(&self, light: PointLight, object_point: Point3) -> Color {
+        match self {
+            Material::Phong(m) => m.ambient(light, object_point),
+            Material::Pbr(m) => m.ambient(light, object_point),
+        }
+    }
+
+    fn direct(
+        &self,
+        light: PointLight,
+        world_point: Point3,
+        object_point: Point3,
+        eye: Vector3,
+        surface_normal: Normal3,
+    ) -> Color {
+        match self {
+            Material::Phong(m) => m.direct(light, world_point, object_point, eye, surface_normal),
+            Material::Pbr(m) => m.direct(light, world_point, object_point, eye, surface_normal),
+        }
+    }
+
+    fn albedo(&self, object_point: Point3) -> Color {
+        match self {
+            Material::Phong(m) => m.albedo(object_point),
+            Material::Pbr(m) => m.albedo(object_point),
+        }
+    }
+}
+
+impl Default for Material {
+    fn default() -> Material {
+        Material::Phong(PhongMaterial::default())
+    }
+}
+
+impl From<PhongMaterial> for Material {
+    fn from(m: PhongMaterial) -> Material {
+        Material::Phong(m)
+    }
+}
+
+impl From<PbrMaterial> for Material {
+    fn from(m: PbrMaterial) -> Material {
+        Material::Pbr(m)
+    }
+}
+
 #[derive(Debug, Clone)]
 struct LightHit<'a> {
-    pub world_point: Tuple,
-    pub object_point: Tuple,
+    // the distance along the ray to the hit
+    pub t: Float,
+    pub object: &'a dyn Shape,
+    pub world_point: Point3,
+    pub object_point: Point3,
     // a point slightly above the surface in world space, used to cast shadow rays
-    pub over_point: Tuple,
-    pub surface_normal: Tuple,
-    pub to_eye: Tuple,
-    pub material: &'a PhongMaterial,
+    pub over_point: Point3,
+    pub surface_normal: Normal3,
+    pub to_eye: Vector3,
+    pub material: &'a Material,
+    // see `Ray::time`: any rays cast from this hit need to happen at the same time
+    pub time: Float,
     // whether the light ray hit the inside surface of the object.
     // in this case surface_normal is reversed to provide a useful value
     pub inside: bool,
@@ -64,11 +219,11 @@ struct LightHit<'a> {
 
 fn prepare_computations<'a>(hit: Intersection<'a>, ray: Ray) -> Option<LightHit<'a>> {
     let world_point = ray.position(hit.t);
-    let object_point = hit.obj.world_to_object() * world_point;
+    let object_point = hit.obj.world_to_object_at_time(ray.time) * world_point;
 
     let to_eye = -ray.direction;
 
-    let mut surface_normal = hit.obj.normal_at(world_point);
+    let mut surface_normal = hit.obj.normal_at_time(world_point, ray.time);
 
     let mut inside = false;
     if surface_normal.dot(to_eye) < 0. {
@@ -78,89 +233,56 @@ fn prepare_computations<'a>(hit: Intersection<'a>, ray: Ray) -> Option<LightHit<
         inside = true;
     }
 
-    // TODO: this epsilon seems a bit big, but smaller values cause lots of artifacts
-    let over_point = world_point + (surface_normal.normalize() * 0.0001);
+    let over_point = world_point + (surface_normal.normalize() * EPSILON);
 
     let material = hit.obj.material();
 
     Some(LightHit {
+        t: hit.t,
+        object: hit.obj,
         world_point,
         object_point,
         over_point,
         surface_normal,
         material,
+        time: ray.time,
         to_eye,
         inside,
     })
 }
 
 fn light_ray<'a>(world: &'a World, ray: Ray) -> Option<LightHit<'a>> {
-    // TODO: HACK: this inlines a bunch of methods (World.ray_intersection, Intersection::hit, etc)
-    // since I can't figure out how to tell the borrow checker that those methods are doing the right thing.
-
-    let mut result = Vec::<Intersection>::new();
-    for obj in &world.objects {
-        let intersection = obj.ray_intersection(ray);
-        result.extend_from_slice(&intersection);
-    }
-    result.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap_or(std::cmp::Ordering::Equal));
-
-    let hit = result.into_iter().filter(|a| a.t >= 0.).next()?;
+    let hit = world.closest_hit(ray, 0., Float::INFINITY)?;
 
     prepare_computations(hit, ray)
 }
 
-fn lighting(
-    material: &PhongMaterial,
+fn lighting<M: LightingModel + ?Sized>(
+    material: &M,
     light: PointLight,
-    world_point: Tuple,
-    object_point: Tuple,
-    eye: Tuple,
-    surface_normal: Tuple,
+    world_point: Point3,
+    object_point: Point3,
+    eye: Vector3,
+    surface_normal: Normal3,
     is_shadow: bool,
 ) -> Color {
-    assert!(world_point.is_point());
     // `eye` is a vector from `surface_position` to the eye position
-    assert!(eye.is_vec());
-    assert!(surface_normal.is_vec());
 
-    let color = material.pattern.sample_pattern_at(object_point);
-    let effective_color = color * light.intensity;
-    let light_direction = (light.position - world_point).normalize();
-    let ambient = effective_color * material.ambient;
+    let ambient = material.ambient(light, object_point);
 
     if is_shadow {
         return ambient;
     }
 
-    let cos_light_angle = light_direction.dot(surface_normal);
-    let diffuse = match cos_light_angle {
-        x if x < 0. => black(),                      // light is behind surface normal
-        x => effective_color * material.diffuse * x, // light is in front, modified by angle
-    };
-    let specular = match cos_light_angle {
-        x if x < 0. => black(),
-        _ => {
-            let cos_reflection_angle = reflect(-light_direction, surface_normal).dot(eye);
-            match cos_reflection_angle {
-                x if x < 0. => black(),
-                x => {
-                    let factor = x.powf(material.shininess);
-                    light.intensity * material.specular * factor
-                }
-            }
-        }
-    };
-
-    ambient + diffuse + specular
+    ambient + material.direct(light, world_point, object_point, eye, surface_normal)
 }
 
 fn shade_hit(world: &World, hit: LightHit) -> Color {
-    let mut result = black();
+    // emitted light doesn't depend on any lights, so it's only added once
+    let mut result = hit.material.emissive(hit.object_point);
 
     for light in &world.lights {
-        // TODO: is_shadowed should probably take a light instead of a world
-        let is_shadowed = is_shadowed(world, hit.over_point);
+        let is_shadowed = is_shadowed(world, *light, hit.over_point, hit.time);
 
         result += lighting(
             hit.material,
@@ -179,19 +301,23 @@ fn shade_hit(world: &World, hit: LightHit) -> Color {
 pub fn color_at(world: &World, ray: Ray) -> Color {
     light_ray(world, ray)
         .map(|h| shade_hit(world, h))
-        .unwrap_or(black())
+        .unwrap_or_else(|| world.background.color_in(ray.direction))
 }
 
-fn is_shadowed(world: &World, point: Tuple) -> bool {
-    assert!(point.is_point());
-    // TODO: support more than one light
-    let light = world.lights[0];
+// like `color_at`, when the ray's closest hit has already been found (eg by `World::packet_hits`)
+pub fn color_for_hit(world: &World, ray: Ray, hit: Option<Intersection>) -> Color {
+    hit.and_then(|h| prepare_computations(h, ray))
+        .map(|h| shade_hit(world, h))
+        .unwrap_or_else(|| world.background.color_in(ray.direction))
+}
+
+fn is_shadowed(world: &World, light: PointLight, point: Point3, time: Float) -> bool {
     let point_to_light = light.position - point;
     let distance_to_light = point_to_light.magnitude();
     let direction = point_to_light.normalize();
 
-    let intersections = world.ray_intersection(Ray::new(point, direction));
-    let hit = Intersection::hit(&intersections);
+    let ray = Ray::new(point, direction).with_time(time);
+    let hit = world.closest_hit(ray, 0., distance_to_light);
 
     // println!(
     //     "Casting shadow ray from {:?} to light at {:?} and hit {:?}",
@@ -212,7 +338,7 @@ mod tests {
         let material = PhongMaterial::default();
         let surface_position = point(0., 0., 0.);
         let eye = vec(0., 0., -1.);
-        let normal = vec(0., 0., -1.);
+        let normal = Normal3::new(0., 0., -1.);
         let light = PointLight::new(white(), point(0., 0., -10.));
         let result = lighting(
             &material,
@@ -231,9 +357,9 @@ mod tests {
     fn viewing_at_45_deg() {
         let material = PhongMaterial::default();
         let surface_position = point(0., 0., 0.);
-        let s22 = 2_f32.sqrt() / 2.;
+        let s22 = Float::sqrt(2.) / 2.;
         let eye = vec(0., s22, s22);
-        let normal = vec(0., 0., -1.);
+        let normal = Normal3::new(0., 0., -1.);
         let light = PointLight::new(white(), point(0., 0., -10.));
         let result = lighting(
             &material,
@@ -253,7 +379,7 @@ mod tests {
         let material = PhongMaterial::default();
         let surface_position = point(0., 0., 0.);
         let eye = vec(0., 0., -1.);
-        let normal = vec(0., 0., -1.);
+        let normal = Normal3::new(0., 0., -1.);
         let light = PointLight::new(white(), point(0., 10., -10.));
         let result = lighting(
             &material,
@@ -272,9 +398,9 @@ mod tests {
     fn light_and_eye_at_45_deg() {
         let material = PhongMaterial::default();
         let surface_position = point(0., 0., 0.);
-        let s22 = 2_f32.sqrt() / 2.;
+        let s22 = Float::sqrt(2.) / 2.;
         let eye = vec(0., -s22, -s22);
-        let normal = vec(0., 0., -1.);
+        let normal = Normal3::new(0., 0., -1.);
         let light = PointLight::new(white(), point(0., 10., -10.));
         let result = lighting(
             &material,
@@ -301,7 +427,7 @@ mod tests {
         let material = PhongMaterial::default();
         let surface_position = point(0., 0., 0.);
         let eye = vec(0., 0., -1.);
-        let normal = vec(0., 0., -1.);
+        let normal = Normal3::new(0., 0., -1.);
         let light = PointLight::new(white(), point(0., 0., 10.));
         let result = lighting(
             &material,
@@ -323,7 +449,7 @@ mod tests {
         let hit = Intersection::ray_sphere(shape, 4.);
         let intersection = prepare_computations(hit, r).unwrap();
 
-        assert_eq!(vec(0., 0., -1.), intersection.surface_normal);
+        assert_eq!(Normal3::new(0., 0., -1.), intersection.surface_normal);
         assert_eq!(false, intersection.inside);
     }
 
@@ -336,7 +462,7 @@ mod tests {
 
         // since we're hitting the +ve z side of the sphere, the outside normal is (0,0,+1)
         // but it's inverted since we're hitting the inside
-        assert_eq!(vec(0., 0., -1.), intersection.surface_normal);
+        assert_eq!(Normal3::new(0., 0., -1.), intersection.surface_normal);
         assert_eq!(true, intersection.inside);
     }
 
@@ -375,6 +501,18 @@ mod tests {
         unimplemented!();
     }
 
+    #[test]
+    fn missed_rays_show_the_background() {
+        let r = Ray::new(point(0., 0., -5.), vec(0., 1., 0.));
+        assert_eq!(black(), color_at(&World::default(), r));
+
+        let w = World::default().with_background(Background::Gradient {
+            bottom: black(),
+            top: Color::new(0.5, 0.7, 1.),
+        });
+        assert_eq!(Color::new(0.5, 0.7, 1.), color_at(&w, r));
+    }
+
     #[test]
     fn shade_hit_with_an_intersection_in_shadow() {
         let s1 = Sphere::unit();
@@ -388,12 +526,45 @@ mod tests {
         assert_eq!(Color::new(0.1, 0.1, 0.1), c);
     }
 
+    #[test]
+    fn shade_hit_adds_emissive_light_without_any_lights() {
+        let mut s = Sphere::unit();
+        s.material = PhongMaterial {
+            emissive: solid(Color::new(0.2, 0.4, 0.6)),
+            ..Default::default()
+        }
+        .into();
+        let w = World::new(vec![Box::new(s)], vec![]);
+
+        let hit = light_ray(&w, Ray::new(point(0., 0., -5.), vec(0., 0., 1.))).unwrap();
+        assert_eq!(Color::new(0.2, 0.4, 0.6), shade_hit(&w, hit));
+    }
+
+    #[test]
+    fn emissive_light_is_not_affected_by_shadows() {
+        let s1 = Sphere::unit();
+        let mut s2 = Sphere::pos_r(point(0., 0., 10.), 1.);
+        s2.material = PhongMaterial {
+            emissive: solid(Color::new(0.5, 0., 0.)),
+            ..Default::default()
+        }
+        .into();
+        let l = PointLight::new(white(), point(0., 0., -10.));
+        let w = World::new(vec![Box::new(s1), Box::new(s2)], vec![l]);
+
+        let hit = light_ray(&w, Ray::new(point(0., 0., 5.), vec(0., 0., 1.))).unwrap();
+
+        // ambient from the (shadowed) light, plus the emitted light
+        let c = shade_hit(&w, hit);
+        assert_color_eq!(Color::new(0.6, 0.1, 0.1), c);
+    }
+
     #[test]
     fn lighting_with_surface_in_shadow() {
         let material = PhongMaterial::default();
         let surface_position = point(0., 0., 0.);
         let eye = vec(0., 0., -1.);
-        let normal = vec(0., 0., -1.);
+        let normal = Normal3::new(0., 0., -1.);
         let light = PointLight::new(white(), point(0., 0., -10.));
         let is_shadow = true;
         let result = lighting(
@@ -421,8 +592,7 @@ mod tests {
         let w = World::new(vec![Box::new(s)], vec![]);
         let hit = light_ray(&w, r).unwrap();
 
-        // see TODO about large epsilon in prepare_computations
-        assert!(hit.over_point.z > -0.0002);
+        assert!(hit.over_point.z > -2. * EPSILON);
         assert!(hit.world_point.z > hit.over_point.z);
     }
 
@@ -434,7 +604,7 @@ mod tests {
             // light is in top-left-behind quadrant, and object is on top the y axis
             let w = World::default();
             let p = point(0., 10., 0.);
-            assert_eq!(false, is_shadowed(&w, p));
+            assert_eq!(false, is_shadowed(&w, w.lights[0], p, 0.));
         }
 
         #[test]
@@ -443,7 +613,7 @@ mod tests {
             // the center sphere is between them
             let w = World::default();
             let p = point(10., -10., 10.);
-            assert_eq!(true, is_shadowed(&w, p));
+            assert_eq!(true, is_shadowed(&w, w.lights[0], p, 0.));
         }
 
         #[test]
@@ -451,7 +621,7 @@ mod tests {
             // light is in top-left-behind quadrant, and point is further out in the same direction
             let w = World::default();
             let p = point(-20., 20., -20.);
-            assert_eq!(false, is_shadowed(&w, p));
+            assert_eq!(false, is_shadowed(&w, w.lights[0], p, 0.));
         }
 
         #[test]
@@ -459,7 +629,7 @@ mod tests {
             // light is in top-left-behind quadrant, and point is between it and the origin
             let w = World::default();
             let p = point(-5., 5., -5.);
-            assert_eq!(false, is_shadowed(&w, p));
+            assert_eq!(false, is_shadowed(&w, w.lights[0], p, 0.));
         }
     }
 }
@@ -0,0 +1,164 @@
+use super::*;
+
+// replaces the flat ambient term with an estimate of how much of the sky
+// each point can see: points in corners and crevices receive less ambient light
+#[derive(Debug, Clone, PartialEq)]
+pub struct AmbientOcclusion {
+    // the number of rays cast from each point
+    pub samples: usize,
+    // objects further away than this don't count as occluding the point
+    pub max_distance: Float,
+}
+
+impl AmbientOcclusion {
+    pub fn new(samples: usize, max_distance: Float) -> AmbientOcclusion {
+        AmbientOcclusion {
+            samples,
+            max_distance,
+        }
+    }
+
+    // shade the hit like the Whitted integrator, but with ambient occlusion
+    pub fn color_at(&self, world: &World, ray: Ray, rng: &mut Rng) -> Color {
+        self.color_for_hit(world, ray, light_ray(world, ray), rng)
+    }
+
+    pub(super) fn color_for_hit(
+        &self,
+        world: &World,
+        ray: Ray,
+        hit: Option<LightHit>,
+        rng: &mut Rng,
+    ) -> Color {
+        hit.map(|h| {
+            let visibility = self.visibility(world, &h, rng);
+            shade_hit_occluded(world, h, visibility)
+        })
+        .unwrap_or_else(|| world.background.color_in(ray.direction))
+    }
+
+    // a greyscale image of the occlusion itself: white where nothing is occluded
+    pub fn occlusion_at(&self, world: &World, ray: Ray, rng: &mut Rng) -> Color {
+        self.occlusion_for_hit(world, light_ray(world, ray), rng)
+    }
+
+    pub(super) fn occlusion_for_hit(
+        &self,
+        world: &World,
+        hit: Option<LightHit>,
+        rng: &mut Rng,
+    ) -> Color {
+        hit.map(|h| grey(self.visibility(world, &h, rng)))
+            .unwrap_or(white())
+    }
+
+    // the fraction of rays cast into the hemisphere above the hit which escape
+    fn visibility(&self, world: &World, hit: &LightHit, rng: &mut Rng) -> Float {
+        if self.samples == 0 {
+            return 1.;
+        }
+
+        // cosine-weighted rays, since light arriving at a grazing angle contributes less
+        let unoccluded = (0..self.samples)
+            .filter(|_| {
+                let direction = cosine_sample_hemisphere(hit.surface_normal, rng);
+                let ray = Ray::new(hit.over_point, direction).with_time(hit.time);
+                let hit = world.closest_hit(ray, 0., self.max_distance);
+                hit.map(|i| i.t >= self.max_distance).unwrap_or(true)
+            })
+            .count();
+
+        unoccluded as Float / self.samples as Float
+    }
+}
+
+fn shade_hit_occluded(world: &World, hit: LightHit, visibility: Float) -> Color {
+    let mut result = hit.material.emissive(hit.object_point);
+
+    for light in &world.lights {
+        result += hit.material.ambient(*light, hit.object_point) * visibility;
+
+        if !is_shadowed(world, *light, hit.over_point, hit.time) {
+            result += hit.material.direct(
+                *light,
+                hit.world_point,
+                hit.object_point,
+                hit.to_eye,
+                hit.surface_normal,
+            );
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nothing_occludes_a_lone_plane() {
+        let w = World::new(vec![Box::new(Plane::xz())], vec![]);
+        let ao = AmbientOcclusion::new(32, 10.);
+        let r = Ray::new(point(0., 1., -1.), vec(0., -1., 0.));
+        assert_eq!(white(), ao.occlusion_at(&w, r, &mut Rng::new(0)));
+    }
+
+    #[test]
+    fn the_inside_of_a_sphere_is_fully_occluded() {
+        let w = World::new(vec![Box::new(Sphere::unit())], vec![]);
+        let ao = AmbientOcclusion::new(32, 10.);
+        let r = Ray::new(point(0., 0., 0.), vec(0., 0., 1.));
+        assert_eq!(black(), ao.occlusion_at(&w, r, &mut Rng::new(0)));
+
+        // unless the sphere is further away than the max distance
+        let ao2 = AmbientOcclusion::new(32, 0.0001);
+        assert_eq!(white(), ao2.occlusion_at(&w, r, &mut Rng::new(0)));
+    }
+
+    #[test]
+    fn nearby_objects_partially_occlude_a_point() {
+        let floor = Plane::xz();
+        let sphere = Sphere::pos_r(point(0., 1.2, 0.), 1.);
+        let w = World::new(vec![Box::new(floor), Box::new(sphere)], vec![]);
+        let ao = AmbientOcclusion::new(256, 5.);
+        let r = Ray::new(point(1., 5., -0.5), vec(0., -1., 0.));
+        let c = ao.occlusion_at(&w, r, &mut Rng::new(0));
+        // the ray misses the sphere and hits the floor just to the side of it
+        assert!(c.r > 0.2 && c.r < 0.8, "{:?}", c);
+    }
+
+    #[test]
+    fn missed_rays_are_unoccluded() {
+        let w = World::default();
+        let ao = AmbientOcclusion::new(8, 1.);
+        let r = Ray::new(point(0., 0., -5.), vec(0., 1., 0.));
+        assert_eq!(white(), ao.occlusion_at(&w, r, &mut Rng::new(0)));
+        assert_eq!(black(), ao.color_at(&w, r, &mut Rng::new(0)));
+    }
+
+    #[test]
+    fn unoccluded_points_are_shaded_like_whitted() {
+        let w = World::default();
+        let ao = AmbientOcclusion::new(16, 0.0001);
+        let r = Ray::new(point(0., 0., -5.), vec(0., 0., 1.));
+        assert_color_eq!(
+            color_at(&w, r),
+            ao.color_at(&w, r, &mut Rng::new(0)),
+            epsilon = 0.00001
+        );
+    }
+
+    #[test]
+    fn occlusion_only_affects_the_ambient_term() {
+        // from inside a sphere, all of the ambient light is occluded but the light still reaches
+        let mut w = World::default();
+        w.lights[0] = PointLight::new(white(), point(0., 0.25, 0.));
+        let r = Ray::new(point(0., 0., 0.), vec(0., 0., 1.));
+        let ao = AmbientOcclusion::new(16, 10.);
+        let c = ao.color_at(&w, r, &mut Rng::new(0));
+
+        // this is `shade_hit_from_inside_sphere`, minus 0.1 ambient
+        assert_color_eq!(grey(0.80498), c, epsilon = 0.00001);
+    }
+}
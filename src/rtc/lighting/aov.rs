@@ -0,0 +1,142 @@
+use super::*;
+
+// "arbitrary output variables": debug images showing some property of the
+// surface that each camera ray hits, instead of the shaded color
+#[derive(Debug, Clone, PartialEq)]
+pub enum Aov {
+    // the distance to the hit, where black is at the camera and white is `max_distance` or further
+    Depth { max_distance: Float },
+    // the world space surface normal, with each component mapped from [-1, 1] to [0, 1]
+    Normal,
+    // a different (arbitrary) color for each object in the world
+    ObjectId,
+    // the hit point in the object's local coordinate space, as (x, y, z) => (r, g, b)
+    ObjectPoint,
+    // the surface's texture coordinates, as (u, v) => (r, g)
+    Uv,
+}
+
+impl Aov {
+    pub fn color_at(&self, world: &World, ray: Ray) -> Color {
+        self.color_for_hit(world, light_ray(world, ray))
+    }
+
+    pub(super) fn color_for_hit(&self, world: &World, hit: Option<LightHit>) -> Color {
+        match hit {
+            Some(hit) => self.color_for_surface(world, &hit),
+            None => match self {
+                // missing everything is the same as being infinitely far away
+                Aov::Depth { .. } => white(),
+                _ => black(),
+            },
+        }
+    }
+
+    fn color_for_surface(&self, world: &World, hit: &LightHit) -> Color {
+        match self {
+            Aov::Depth { max_distance } => grey((hit.t / max_distance).min(1.)),
+            Aov::Normal => {
+                let n = hit.surface_normal;
+                Color::new(n.x * 0.5 + 0.5, n.y * 0.5 + 0.5, n.z * 0.5 + 0.5)
+            }
+            Aov::ObjectId => object_index(world, hit.object)
+                .map(id_color)
+                .unwrap_or(black()),
+            Aov::ObjectPoint => {
+                let p = hit.object_point;
+                Color::new(p.x, p.y, p.z)
+            }
+            Aov::Uv => {
+                let (u, v) = hit.object.local_uv(hit.object_point);
+                Color::new(u, v, 0.)
+            }
+        }
+    }
+}
+
+// the position of `object` in the world's list of objects
+fn object_index(world: &World, object: &dyn Shape) -> Option<usize> {
+    // compare data pointers only, since the vtables may differ between the two trait objects
+    let object = object as *const dyn Shape as *const u8;
+    world
+        .objects
+        .iter()
+        .position(|o| o.as_ref() as *const dyn IntersectableShape as *const u8 == object)
+}
+
+// a bright, random-looking color which is always the same for a given id
+fn id_color(id: usize) -> Color {
+    let mut rng = Rng::new(id as u64);
+    Color::new(
+        0.2 + 0.8 * rng.next_float(),
+        0.2 + 0.8 * rng.next_float(),
+        0.2 + 0.8 * rng.next_float(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ray_at_default_world() -> Ray {
+        Ray::new(point(0., 0., -5.), vec(0., 0., 1.))
+    }
+
+    fn missing_ray() -> Ray {
+        Ray::new(point(0., 0., -5.), vec(0., 1., 0.))
+    }
+
+    #[test]
+    fn depth_is_scaled_by_max_distance() {
+        let w = World::default();
+        let aov = Aov::Depth { max_distance: 8. };
+        assert_eq!(grey(0.5), aov.color_at(&w, ray_at_default_world()));
+        assert_eq!(white(), aov.color_at(&w, missing_ray()));
+
+        let aov2 = Aov::Depth { max_distance: 2. };
+        assert_eq!(white(), aov2.color_at(&w, ray_at_default_world()));
+    }
+
+    #[test]
+    fn normals_are_mapped_to_positive_colors() {
+        let w = World::default();
+        // the ray hits the front of the sphere, where the normal is (0, 0, -1)
+        assert_eq!(
+            Color::new(0.5, 0.5, 0.),
+            Aov::Normal.color_at(&w, ray_at_default_world())
+        );
+        assert_eq!(black(), Aov::Normal.color_at(&w, missing_ray()));
+    }
+
+    #[test]
+    fn each_object_gets_its_own_id_color() {
+        let w = World::default();
+        let outer = Aov::ObjectId.color_at(&w, ray_at_default_world());
+        // starting inside the outer sphere, we hit the inner one first
+        let inner = Aov::ObjectId.color_at(&w, Ray::new(point(0., 0., -0.9), vec(0., 0., 1.)));
+
+        assert_eq!(id_color(0), outer);
+        assert_eq!(id_color(1), inner);
+        assert_ne!(outer, inner);
+        assert_eq!(black(), Aov::ObjectId.color_at(&w, missing_ray()));
+    }
+
+    #[test]
+    fn object_point_is_in_object_space() {
+        let s = Sphere::pos_r(point(0., 0., 10.), 2.);
+        let w = World::new(vec![Box::new(s)], vec![]);
+        let r = Ray::new(point(0., 0., 0.), vec(0., 0., 1.));
+        assert_color_eq!(
+            Color::new(0., 0., -1.),
+            Aov::ObjectPoint.color_at(&w, r),
+            epsilon = 0.0001
+        );
+    }
+
+    #[test]
+    fn uv_uses_the_shapes_texture_coordinates() {
+        let w = World::new(vec![Box::new(Plane::xz())], vec![]);
+        let r = Ray::new(point(2.25, 1., -0.5), vec(0., -1., 0.));
+        assert_eq!(Color::new(0.25, 0.5, 0.), Aov::Uv.color_at(&w, r));
+    }
+}
@@ -0,0 +1,48 @@
+use super::*;
+
+// the algorithm used to work out how much light travels back along a camera ray
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Integrator {
+    // direct lighting from each light, plus a constant ambient term
+    #[default]
+    Whitted,
+    // like `Whitted`, but the ambient term is scaled by ambient occlusion
+    WhittedWithOcclusion(AmbientOcclusion),
+    // Monte Carlo path tracing, which includes indirect light bouncing between objects
+    PathTracer(PathTracer),
+    // a greyscale image of just the ambient occlusion, useful for checking scene geometry
+    Occlusion(AmbientOcclusion),
+    // one of the debug outputs (depth, normals, etc) instead of a shaded color
+    Aov(Aov),
+}
+
+impl Integrator {
+    pub fn color_at(&self, world: &World, ray: Ray, rng: &mut Rng) -> Color {
+        match self {
+            Integrator::Whitted => color_at(world, ray),
+            Integrator::WhittedWithOcclusion(ao) => ao.color_at(world, ray, rng),
+            Integrator::PathTracer(p) => p.color_at(world, ray, rng),
+            Integrator::Occlusion(ao) => ao.occlusion_at(world, ray, rng),
+            Integrator::Aov(aov) => aov.color_at(world, ray),
+        }
+    }
+
+    // the color for a ray whose closest hit is already known (eg found in a packet),
+    // so the ray doesn't need to be traced again
+    pub fn color_for_hit(
+        &self,
+        world: &World,
+        ray: Ray,
+        hit: Option<Intersection>,
+        rng: &mut Rng,
+    ) -> Color {
+        let surface = || hit.and_then(|h| prepare_computations(h, ray));
+        match self {
+            Integrator::Whitted => color_for_hit(world, ray, hit),
+            Integrator::WhittedWithOcclusion(ao) => ao.color_for_hit(world, ray, surface(), rng),
+            Integrator::PathTracer(p) => p.color_for_hit(world, ray, surface(), rng),
+            Integrator::Occlusion(ao) => ao.occlusion_for_hit(world, surface(), rng),
+            Integrator::Aov(aov) => aov.color_for_hit(world, surface()),
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use super::*;
+
+// an unbiased path tracer: at each surface we sample the lights directly
+// (next-event estimation), then continue the path in a random direction to
+// pick up indirect light. Only diffuse reflection is sampled for the indirect
+// part, so any specular highlights come from the direct light only.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathTracer {
+    // paths are always cut off after this many bounces
+    pub max_depth: usize,
+    // after this many bounces, paths are randomly terminated (Russian roulette)
+    // with a probability based on how little light they could still carry
+    pub roulette_depth: usize,
+}
+
+impl PathTracer {
+    pub fn new(max_depth: usize, roulette_depth: usize) -> PathTracer {
+        PathTracer {
+            max_depth,
+            roulette_depth,
+        }
+    }
+
+    pub fn color_at(&self, world: &World, ray: Ray, rng: &mut Rng) -> Color {
+        self.color_for_hit(world, ray, light_ray(world, ray), rng)
+    }
+
+    // like `color_at`, when the first hit along the path has already been found
+    pub(super) fn color_for_hit(
+        &self,
+        world: &World,
+        ray: Ray,
+        hit: Option<LightHit>,
+        rng: &mut Rng,
+    ) -> Color {
+        let mut result = black();
+        // how much of the light arriving at the current surface makes it back to the camera
+        let mut throughput = white();
+        let mut ray = ray;
+        let mut first_hit = Some(hit);
+
+        for depth in 0..self.max_depth {
+            let hit = match first_hit.take().unwrap_or_else(|| light_ray(world, ray)) {
+                Some(hit) => hit,
+                None => {
+                    // the background lights the scene from every direction, but since it's
+                    // not sampled directly it only shows up when a path escapes into it
+                    result += throughput * world.background.color_in(ray.direction);
+                    break;
+                }
+            };
+
+            // emissive surfaces aren't sampled as lights below, so we can count
+            // their light whenever a path happens to hit them without double-counting
+            result += throughput * hit.material.emissive(hit.object_point);
+
+            for light in &world.lights {
+                if !is_shadowed(world, *light, hit.over_point, hit.time) {
+                    result += throughput
+                        * hit.material.direct(
+                            *light,
+                            hit.world_point,
+                            hit.object_point,
+                            hit.to_eye,
+                            hit.surface_normal,
+                        );
+                }
+            }
+
+            // with cosine-weighted sampling, the cos(theta)/pi factor in the rendering
+            // equation cancels out with the sample's probability, leaving only the albedo
+            let albedo = hit.material.albedo(hit.object_point);
+            throughput = throughput * albedo;
+
+            if depth >= self.roulette_depth {
+                let survival = throughput.r.max(throughput.g).max(throughput.b).min(1.);
+                if rng.next_float() >= survival {
+                    break;
+                }
+                // boost the paths that survive to make up for the ones that didn't
+                throughput = throughput * (1. / survival);
+            }
+
+            let direction = cosine_sample_hemisphere(hit.surface_normal, rng);
+            ray = Ray::new(hit.over_point, direction).with_time(hit.time);
+        }
+
+        result
+    }
+}
+
+impl Default for PathTracer {
+    fn default() -> PathTracer {
+        PathTracer::new(8, 3)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missed_rays_are_black() {
+        let w = World::default();
+        let mut rng = Rng::new(0);
+        let r = Ray::new(point(0., 0., -5.), vec(0., 1., 0.));
+        assert_eq!(black(), PathTracer::default().color_at(&w, r, &mut rng));
+    }
+
+    #[test]
+    fn the_background_lights_the_scene() {
+        // there are no lights, just a floor under a white sky
+        let w = World::new(vec![Box::new(Plane::xz())], vec![])
+            .with_background(Background::Solid(white()));
+        let mut rng = Rng::new(0);
+        let p = PathTracer::default();
+
+        assert_eq!(
+            white(),
+            p.color_at(&w, Ray::new(point(0., 1., 0.), vec(0., 1., 0.)), &mut rng)
+        );
+        // every bounce off the floor escapes into the sky, so the floor shows its albedo
+        assert_color_eq!(
+            grey(0.9),
+            p.color_at(&w, Ray::new(point(0., 1., -1.), vec(0., -1., 0.)), &mut rng),
+            epsilon = 0.00001
+        );
+    }
+
+    #[test]
+    fn single_bounce_is_direct_lighting_without_ambient() {
+        let w = World::default();
+        let mut rng = Rng::new(0);
+        let r = Ray::new(point(0., 0., -5.), vec(0., 0., 1.));
+        let c = PathTracer::new(1, 1).color_at(&w, r, &mut rng);
+
+        // this is `shade_hit_from_outside_sphere`, minus the 0.1 * (0.8, 1, 0.6) ambient term
+        assert_color_eq!(Color::new(0.30066, 0.37583, 0.2255), c, epsilon = 0.00001);
+    }
+
+    #[test]
+    fn paths_are_deterministic_for_a_given_seed() {
+        let w = World::default();
+        let r = Ray::new(point(0., 0., -5.), vec(0., 0., 1.));
+        let p = PathTracer::default();
+        let c1 = p.color_at(&w, r, &mut Rng::new(3));
+        let c2 = p.color_at(&w, r, &mut Rng::new(3));
+        assert_eq!(c1, c2);
+    }
+
+    #[test]
+    fn emissive_objects_light_up_the_scene() {
+        // there are no lights, only a glowing sphere above a floor
+        let floor = Plane::xz();
+        let mut lamp = Sphere::pos_r(point(0., 3., 0.), 1.);
+        lamp.material = PhongMaterial {
+            emissive: solid(white()),
+            ..Default::default()
+        }
+        .into();
+        let w = World::new(vec![Box::new(floor), Box::new(lamp)], vec![]);
+        assert_eq!(
+            black(),
+            color_at(&w, Ray::new(point(0., 1., -1.), vec(0., -1., 0.)))
+        );
+
+        let p = PathTracer::default();
+        let mut rng = Rng::new(0);
+        let n = 500;
+        let mut total = black();
+        for _ in 0..n {
+            total += p.color_at(&w, Ray::new(point(0., 1., -1.), vec(0., -1., 0.)), &mut rng);
+        }
+        let average = total * (1. / n as Float);
+
+        // some of the bounces from the floor hit the lamp
+        assert!(average.r > 0.05);
+        assert!(average.r < 1.);
+    }
+
+    #[test]
+    fn indirect_light_picks_up_color_from_nearby_objects() {
+        // a white floor next to a big red sphere, with the light directly above
+        let floor = Plane::xz();
+        let mut red_sphere = Sphere::pos_r(point(1.5, 1., 0.), 1.);
+        red_sphere.material = PhongMaterial::solid(red(), 0.1, 0.9, 0., 200.).into();
+        let light = PointLight::new(white(), point(0., 10., 0.));
+        let w = World::new(vec![Box::new(floor), Box::new(red_sphere)], vec![light]);
+
+        let p = PathTracer::default();
+        let mut rng = Rng::new(0);
+        let n = 500;
+        let mut total = black();
+        for _ in 0..n {
+            total += p.color_at(&w, Ray::new(point(0., 1., -1.), vec(0., -1., 0.)), &mut rng);
+        }
+        let average = total * (1. / n as Float);
+
+        // the floor is lit white, but tinted red by light bouncing off the sphere
+        assert!(average.r > average.g);
+        assert!((average.g - average.b).abs() < 0.0001);
+    }
+}
@@ -2,32 +2,26 @@ use crate::*;
 use rtc::na::{ArrayStorage, Matrix, U2, U3, U4};
 use std::ops::Mul;
 
-pub type Matrix4 = Matrix<f32, U4, U4, ArrayStorage<f32, U4, U4>>;
-pub type Matrix3 = Matrix<f32, U3, U3, ArrayStorage<f32, U3, U3>>;
-pub type Matrix2 = Matrix<f32, U2, U2, ArrayStorage<f32, U2, U2>>;
-
-impl Mul<Tuple> for Matrix4 {
-    type Output = Tuple;
-
-    fn mul(self, other: Tuple) -> Tuple {
-        Tuple::new(
-            self[(0, 0)] * other.x
-                + self[(0, 1)] * other.y
-                + self[(0, 2)] * other.z
-                + self[(0, 3)] * other.w,
-            self[(1, 0)] * other.x
-                + self[(1, 1)] * other.y
-                + self[(1, 2)] * other.z
-                + self[(1, 3)] * other.w,
-            self[(2, 0)] * other.x
-                + self[(2, 1)] * other.y
-                + self[(2, 2)] * other.z
-                + self[(2, 3)] * other.w,
-            self[(3, 0)] * other.x
-                + self[(3, 1)] * other.y
-                + self[(3, 2)] * other.z
-                + self[(3, 3)] * other.w,
-        )
+pub type Matrix4 = Matrix<Float, U4, U4, ArrayStorage<Float, U4, U4>>;
+pub type Matrix3 = Matrix<Float, U3, U3, ArrayStorage<Float, U3, U3>>;
+pub type Matrix2 = Matrix<Float, U2, U2, ArrayStorage<Float, U2, U2>>;
+
+// points are affected by translations (as if they had w = 1), but vectors aren't
+// (w = 0). All of our transforms are affine, so the bottom row of the matrix is ignored.
+// The arithmetic is done a column at a time using SIMD lanes, see `transform_point`.
+impl Mul<Point3> for Matrix4 {
+    type Output = Point3;
+
+    fn mul(self, other: Point3) -> Point3 {
+        transform_point(&self, other)
+    }
+}
+
+impl Mul<Vector3> for Matrix4 {
+    type Output = Vector3;
+
+    fn mul(self, other: Vector3) -> Vector3 {
+        transform_vector(&self, other)
     }
 }
 
@@ -120,18 +114,20 @@ mod tests {
     }
 
     #[test]
-    fn matrix_multiplication_with_tuple() {
+    fn matrix_multiplication_with_points_and_vectors() {
         let a = Matrix4::new(
             1., 2., 3., 4., //
             2., 4., 4., 2., //
             8., 6., 4., 1., //
             0., 0., 0., 1.,
         );
-        let b = Tuple::new(1., 2., 3., 1.);
-
-        assert_eq!(Tuple::new(18., 24., 33., 1.), a * b);
+        let b = point(1., 2., 3.);
 
+        assert_eq!(point(18., 24., 33.), a * b);
         assert_eq!(b, Matrix4::identity() * b);
+
+        // vectors ignore the translation in the last column
+        assert_eq!(vec(14., 22., 32.), a * vec(1., 2., 3.));
     }
 
     #[test]
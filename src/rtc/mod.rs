@@ -3,6 +3,10 @@ mod asserts;
 pub use self::asserts::*;
 
 extern crate nalgebra as na;
+mod float;
+pub use self::float::*;
+mod error;
+pub use self::error::*;
 mod canvas;
 pub use self::canvas::*;
 mod color;
@@ -15,15 +19,33 @@ mod rays;
 pub use self::rays::*;
 mod shapes;
 pub use self::shapes::*;
+mod bounds;
+pub use self::bounds::*;
 mod transformations;
 pub use self::transformations::*;
+mod quaternion;
+pub use self::quaternion::*;
 mod tuple;
 pub use self::tuple::*;
+mod simd;
+pub use self::simd::*;
 mod lighting;
 pub use self::lighting::*;
 mod world;
 pub use self::world::*;
+mod background;
+pub use self::background::*;
 mod camera;
 pub use self::camera::*;
 mod patterns;
 pub use self::patterns::*;
+mod pbr;
+pub use self::pbr::*;
+mod sampling;
+pub use self::sampling::*;
+mod tone_mapping;
+pub use self::tone_mapping::*;
+mod post_processing;
+pub use self::post_processing::*;
+mod animation;
+pub use self::animation::*;
@@ -1,10 +1,10 @@
+use crate::consts::PI;
 use crate::*;
-use std::f32::consts::PI;
 
 // TODO: try out trait vs enum when looking into performance work -
 // how much of a difference does it make? (check for Shape as well)
 pub trait SamplePattern {
-    fn sample_pattern_at(&self, p: Tuple) -> Color;
+    fn sample_pattern_at(&self, p: Point3) -> Color;
 }
 
 // this enum/impl pair means we can put Pattern directly into a struct
@@ -20,7 +20,7 @@ pub enum Pattern {
     SphereMap(SphereMap),
 }
 impl SamplePattern for Pattern {
-    fn sample_pattern_at(&self, p: Tuple) -> Color {
+    fn sample_pattern_at(&self, p: Point3) -> Color {
         match self {
             Pattern::Solid(s) => s.sample_pattern_at(p),
             Pattern::Stripe(s) => s.sample_pattern_at(p),
@@ -50,7 +50,7 @@ impl SolidColor {
 }
 
 impl SamplePattern for SolidColor {
-    fn sample_pattern_at(&self, _p: Tuple) -> Color {
+    fn sample_pattern_at(&self, _p: Point3) -> Color {
         self.a
     }
 }
@@ -59,21 +59,21 @@ impl SamplePattern for SolidColor {
 pub struct Stripe {
     a: Box<Pattern>,
     b: Box<Pattern>,
-    // transformation from object space to pattern space
-    object_to_pattern: Matrix4,
+    // transformation from pattern space to object space
+    transform: CachedTransform,
 }
 
 impl Stripe {
-    pub fn new(a: Color, b: Color, transform: Matrix4) -> Stripe {
-        let object_to_pattern = transform
-            .try_inverse()
-            .expect("Stripe transform needs to be invertible");
+    pub fn new(a: Color, b: Color, transform: impl Into<Matrix4>) -> Stripe {
+        Stripe::try_new(a, b, transform).unwrap_or_else(|e| panic!("{}", e))
+    }
 
-        Stripe {
+    pub fn try_new(a: Color, b: Color, transform: impl Into<Matrix4>) -> Result<Stripe, RtcError> {
+        Ok(Stripe {
             a: Box::new(solid(a)),
             b: Box::new(solid(b)),
-            object_to_pattern,
-        }
+            transform: CachedTransform::for_object(transform, "Stripe pattern")?,
+        })
     }
 
     pub fn col(a: Color, b: Color) -> Stripe {
@@ -82,8 +82,8 @@ impl Stripe {
 }
 
 impl SamplePattern for Stripe {
-    fn sample_pattern_at(&self, p: Tuple) -> Color {
-        let p2 = self.object_to_pattern * p;
+    fn sample_pattern_at(&self, p: Point3) -> Color {
+        let p2 = self.transform.inverse() * p;
 
         if p2.x.floor() % 2. == 0. {
             self.a.sample_pattern_at(p2)
@@ -97,20 +97,21 @@ impl SamplePattern for Stripe {
 pub struct Gradient {
     a: Color,
     b: Color,
-    // transformation from object space to pattern space
-    object_to_pattern: Matrix4,
+    // transformation from pattern space to object space
+    transform: CachedTransform,
 }
 impl Gradient {
-    pub fn new(a: Color, b: Color, transform: Matrix4) -> Gradient {
-        let object_to_pattern = transform
-            .try_inverse()
-            .expect("Stripe transform needs to be invertible");
-
-        Gradient {
-            a,
-            b,
-            object_to_pattern,
-        }
+    pub fn new(a: Color, b: Color, transform: impl Into<Matrix4>) -> Gradient {
+        Gradient::try_new(a, b, transform).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_new(
+        a: Color,
+        b: Color,
+        transform: impl Into<Matrix4>,
+    ) -> Result<Gradient, RtcError> {
+        let transform = CachedTransform::for_object(transform, "Gradient pattern")?;
+        Ok(Gradient { a, b, transform })
     }
 
     pub fn col(a: Color, b: Color) -> Gradient {
@@ -118,10 +119,10 @@ impl Gradient {
     }
 }
 impl SamplePattern for Gradient {
-    fn sample_pattern_at(&self, p: Tuple) -> Color {
-        let p2 = self.object_to_pattern * p;
+    fn sample_pattern_at(&self, p: Point3) -> Color {
+        let p2 = self.transform.inverse() * p;
 
-        let a_fac = 1. - 0_f32.max(p2.x).min(1.);
+        let a_fac = 1. - Float::max(0., p2.x).min(1.);
         let b_fac = 1. - a_fac;
         (self.a * a_fac + self.b * b_fac).clamp()
     }
@@ -131,20 +132,17 @@ impl SamplePattern for Gradient {
 pub struct Ring {
     a: Color,
     b: Color,
-    // transformation from object space to pattern space
-    object_to_pattern: Matrix4,
+    // transformation from pattern space to object space
+    transform: CachedTransform,
 }
 impl Ring {
-    pub fn new(a: Color, b: Color, transform: Matrix4) -> Ring {
-        let object_to_pattern = transform
-            .try_inverse()
-            .expect("Stripe transform needs to be invertible");
-
-        Ring {
-            a,
-            b,
-            object_to_pattern,
-        }
+    pub fn new(a: Color, b: Color, transform: impl Into<Matrix4>) -> Ring {
+        Ring::try_new(a, b, transform).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_new(a: Color, b: Color, transform: impl Into<Matrix4>) -> Result<Ring, RtcError> {
+        let transform = CachedTransform::for_object(transform, "Ring pattern")?;
+        Ok(Ring { a, b, transform })
     }
 
     pub fn col(a: Color, b: Color) -> Ring {
@@ -152,8 +150,8 @@ impl Ring {
     }
 }
 impl SamplePattern for Ring {
-    fn sample_pattern_at(&self, p: Tuple) -> Color {
-        let p2 = self.object_to_pattern * p;
+    fn sample_pattern_at(&self, p: Point3) -> Color {
+        let p2 = self.transform.inverse() * p;
 
         let fac = (p2.x * p2.x + p2.z * p2.z).sqrt();
         if fac.floor() % 2. == 0. {
@@ -168,20 +166,24 @@ impl SamplePattern for Ring {
 pub struct Checkerboard {
     a: Box<Pattern>,
     b: Box<Pattern>,
-    // transformation from object space to pattern space
-    object_to_pattern: Matrix4,
+    // transformation from pattern space to object space
+    transform: CachedTransform,
 }
 impl Checkerboard {
-    pub fn new(a: Pattern, b: Pattern, transform: Matrix4) -> Checkerboard {
-        let object_to_pattern = transform
-            .try_inverse()
-            .expect("Stripe transform needs to be invertible");
+    pub fn new(a: Pattern, b: Pattern, transform: impl Into<Matrix4>) -> Checkerboard {
+        Checkerboard::try_new(a, b, transform).unwrap_or_else(|e| panic!("{}", e))
+    }
 
-        Checkerboard {
+    pub fn try_new(
+        a: Pattern,
+        b: Pattern,
+        transform: impl Into<Matrix4>,
+    ) -> Result<Checkerboard, RtcError> {
+        Ok(Checkerboard {
             a: Box::new(a),
             b: Box::new(b),
-            object_to_pattern,
-        }
+            transform: CachedTransform::for_object(transform, "Checkerboard pattern")?,
+        })
     }
 
     pub fn col(a: Color, b: Color) -> Checkerboard {
@@ -193,8 +195,8 @@ impl Checkerboard {
     }
 }
 impl SamplePattern for Checkerboard {
-    fn sample_pattern_at(&self, p: Tuple) -> Color {
-        let mut p2 = self.object_to_pattern * p;
+    fn sample_pattern_at(&self, p: Point3) -> Color {
+        let mut p2 = self.transform.inverse() * p;
         // HACK: since we might want to draw planes that are exactly aligned with a checkerboard boundary
         // (eg the xy plane) but this causes speckles due to float imprecision
         // therefore add an epsilon so that (0,0,0) is firmly within one of the checkerboard cells
@@ -212,19 +214,19 @@ impl SamplePattern for Checkerboard {
 #[derive(Debug, Clone, PartialEq)]
 pub struct SphereMap {
     a: Box<Pattern>,
-    // transformation from object space to pattern space
-    object_to_pattern: Matrix4,
+    // transformation from pattern space to object space
+    transform: CachedTransform,
 }
 impl SphereMap {
-    pub fn new(a: Pattern, transform: Matrix4) -> SphereMap {
-        let object_to_pattern = transform
-            .try_inverse()
-            .expect("Stripe transform needs to be invertible");
+    pub fn new(a: Pattern, transform: impl Into<Matrix4>) -> SphereMap {
+        SphereMap::try_new(a, transform).unwrap_or_else(|e| panic!("{}", e))
+    }
 
-        SphereMap {
+    pub fn try_new(a: Pattern, transform: impl Into<Matrix4>) -> Result<SphereMap, RtcError> {
+        Ok(SphereMap {
             a: Box::new(a),
-            object_to_pattern,
-        }
+            transform: CachedTransform::for_object(transform, "SphereMap pattern")?,
+        })
     }
 
     pub fn col(a: Pattern) -> SphereMap {
@@ -232,24 +234,34 @@ impl SphereMap {
     }
 }
 impl SamplePattern for SphereMap {
-    fn sample_pattern_at(&self, p: Tuple) -> Color {
-        let p2 = self.object_to_pattern * p;
+    fn sample_pattern_at(&self, p: Point3) -> Color {
+        let p2 = self.transform.inverse() * p;
 
-        // based on https://en.wikipedia.org/wiki/UV_mapping#Finding_UV_on_a_sphere
-        // Assuming we have a sphere with y pointing up:
-        //   arctan2(z, x) gives us the angle from the positive x axis around the y axis
-        //   arcsin(y) gives us the angle above or below the xz plane
-        // I think this assumes p2 is a point on the unit sphere
-        //   (since otherwise we'd need to divide y by the
-        //    hypotenuse for the correct value to put in asin())
-
-        let u = 0.5 + (p2.z.atan2(p2.x) / (2. * PI));
-        let v = 0.5 - (p2.y.asin() / PI);
+        let (u, v) = spherical_uv(p2);
 
         self.a.sample_pattern_at(point(u, v, 0.))
     }
 }
 
+pub fn spherical_uv(p: Point3) -> (Float, Float) {
+    // based on https://en.wikipedia.org/wiki/UV_mapping#Finding_UV_on_a_sphere
+    // Assuming we have a sphere with y pointing up:
+    //   arctan2(z, x) gives us the angle from the positive x axis around the y axis
+    //   arcsin(y) gives us the angle above or below the xz plane
+    // I think this assumes p is a point on the unit sphere
+    //   (since otherwise we'd need to divide y by the
+    //    hypotenuse for the correct value to put in asin())
+
+    let u = 0.5 + (p.z.atan2(p.x) / (2. * PI));
+    let v = 0.5 - (p.y.asin() / PI);
+    (u, v)
+}
+
+// maps the xz plane onto a repeating unit square
+pub fn planar_uv(p: Point3) -> (Float, Float) {
+    (p.x - p.x.floor(), p.z - p.z.floor())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -327,4 +339,18 @@ mod tests {
         assert_eq!(white(), p.sample_pattern_at(point(0., 0., 0.99)));
         assert_eq!(black(), p.sample_pattern_at(point(0., 0., 1.01)));
     }
+
+    #[test]
+    fn planar_uv_repeats_every_unit() {
+        assert_eq!((0.25, 0.5), planar_uv(point(0.25, 7., 0.5)));
+        assert_eq!((0.25, 0.5), planar_uv(point(3.25, 0., -1.5)));
+    }
+
+    #[test]
+    fn spherical_uv_wraps_around_the_y_axis() {
+        assert_eq!((0.5, 0.5), spherical_uv(point(1., 0., 0.)));
+        assert_eq!((0.75, 0.5), spherical_uv(point(0., 0., 1.)));
+        assert_eq!((0.5, 0.), spherical_uv(point(0., 1., 0.)));
+        assert_eq!((0.5, 1.), spherical_uv(point(0., -1., 0.)));
+    }
 }
@@ -0,0 +1,214 @@
+use crate::consts::PI;
+use crate::*;
+
+// a physically based material using the Cook-Torrance microfacet model,
+// with the same metallic/roughness parameters that most asset pipelines use.
+// see https://learnopengl.com/PBR/Theory for a good overview of the terms used here
+#[derive(Debug, Clone, PartialEq)]
+pub struct PbrMaterial {
+    pub base_color: Pattern,
+    // 0 for dielectrics (plastic, wood, etc) and 1 for metals
+    pub metallic: Float,
+    // 0 for a perfectly smooth mirror-like surface, 1 for a completely rough one
+    pub roughness: Float,
+    pub ambient: Float,
+    // light given off by the surface itself, regardless of any lights in the scene
+    pub emissive: Pattern,
+}
+
+impl PbrMaterial {
+    pub fn solid(color: Color, metallic: Float, roughness: Float) -> PbrMaterial {
+        PbrMaterial {
+            base_color: solid(color),
+            metallic,
+            roughness,
+            ambient: 0.1,
+            emissive: solid(black()),
+        }
+    }
+}
+
+impl Default for PbrMaterial {
+    fn default() -> PbrMaterial {
+        PbrMaterial::solid(white(), 0., 0.5)
+    }
+}
+
+impl LightingModel for PbrMaterial {
+    fn emissive(&self, object_point: Point3) -> Color {
+        self.emissive.sample_pattern_at(object_point)
+    }
+
+    fn ambient(&self, light: PointLight, object_point: Point3) -> Color {
+        let color = self.base_color.sample_pattern_at(object_point);
+        color * light.intensity() * self.ambient
+    }
+
+    fn direct(
+        &self,
+        light: PointLight,
+        world_point: Point3,
+        object_point: Point3,
+        eye: Vector3,
+        surface_normal: Normal3,
+    ) -> Color {
+        let base_color = self.base_color.sample_pattern_at(object_point);
+        let light_direction = (light.position() - world_point).normalize();
+
+        let n_dot_l = surface_normal.dot(light_direction);
+        if n_dot_l <= 0. {
+            // light is behind surface normal
+            return black();
+        }
+        // clamp to avoid dividing by zero when looking along the surface
+        let n_dot_v = surface_normal.dot(eye).max(0.0001);
+
+        let halfway = (light_direction + eye).normalize();
+        let n_dot_h = surface_normal.dot(halfway).max(0.);
+        let h_dot_v = halfway.dot(eye).max(0.);
+
+        // very small roughness values make the highlight from a point light
+        // infinitely small and bright, so clamp to something renderable
+        let roughness = self.roughness.clamp(0.01, 1.);
+
+        // dielectrics reflect about 4% of light at normal incidence, whereas
+        // metals tint their reflections with the base color
+        let f0 = grey(0.04) * (1. - self.metallic) + base_color * self.metallic;
+
+        let d = distribution_ggx(n_dot_h, roughness);
+        let g = geometry_smith(n_dot_v, n_dot_l, roughness);
+        let f = fresnel_schlick(h_dot_v, f0);
+
+        let specular = f * (d * g / (4. * n_dot_v * n_dot_l));
+        // whatever isn't reflected is refracted and diffused, but metals absorb refracted light
+        let k_diffuse = (white() - f) * (1. - self.metallic);
+        let diffuse = k_diffuse * base_color * (1. / PI);
+
+        // the extra factor of PI means that light intensities mean the same thing
+        // as in the Phong model: a white light shining straight onto a white
+        // diffuse surface reflects all of its light.
+        (diffuse + specular) * light.intensity() * (n_dot_l * PI)
+    }
+
+    // NB: this ignores the specular lobe, so indirect light only bounces off the diffuse part
+    fn albedo(&self, object_point: Point3) -> Color {
+        self.base_color.sample_pattern_at(object_point) * (1. - self.metallic)
+    }
+}
+
+// GGX / Trowbridge-Reitz normal distribution function: the proportion of
+// microfacets which are aligned with the halfway vector
+fn distribution_ggx(n_dot_h: Float, roughness: Float) -> Float {
+    // remapping roughness like this makes it look more perceptually linear
+    let alpha = roughness * roughness;
+    let alpha2 = alpha * alpha;
+    let denom = n_dot_h * n_dot_h * (alpha2 - 1.) + 1.;
+    alpha2 / (PI * denom * denom)
+}
+
+// Smith's method combining Schlick-GGX terms for the view and light directions:
+// the proportion of microfacets which aren't shadowed or obstructed by other microfacets
+fn geometry_smith(n_dot_v: Float, n_dot_l: Float, roughness: Float) -> Float {
+    let k = (roughness + 1.) * (roughness + 1.) / 8.;
+    let schlick_ggx = |n_dot_x: Float| n_dot_x / (n_dot_x * (1. - k) + k);
+    schlick_ggx(n_dot_v) * schlick_ggx(n_dot_l)
+}
+
+// Schlick's approximation of the Fresnel equations: how much light is
+// reflected rather than refracted, which increases at grazing angles
+fn fresnel_schlick(cos_theta: Float, f0: Color) -> Color {
+    f0 + (white() - f0) * (1. - cos_theta).powf(5.)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fresnel_is_f0_at_normal_incidence_and_white_at_grazing_angles() {
+        let f0 = Color::new(0.04, 0.5, 1.);
+        assert_color_eq!(f0, fresnel_schlick(1., f0));
+        assert_color_eq!(white(), fresnel_schlick(0., f0));
+    }
+
+    #[test]
+    fn ggx_distribution_for_fully_rough_surface_is_uniform() {
+        assert_eq!(1. / PI, distribution_ggx(1., 1.));
+        assert_eq!(1. / PI, distribution_ggx(0.5, 1.));
+    }
+
+    #[test]
+    fn smith_geometry_has_no_shadowing_head_on() {
+        assert_eq!(1., geometry_smith(1., 1., 0.3));
+        assert!(geometry_smith(0.1, 1., 0.3) < 1.);
+    }
+
+    #[test]
+    fn rough_dielectric_lit_head_on() {
+        let material = PbrMaterial::solid(white(), 0., 1.);
+        let light = PointLight::new(white(), point(0., 0., -10.));
+        let result = material.direct(
+            light,
+            point(0., 0., 0.),
+            point(0., 0., 0.),
+            vec(0., 0., -1.),
+            Normal3::new(0., 0., -1.),
+        );
+        // 96% of the light is diffused, plus a small specular contribution of F*D*G/4
+        assert_color_eq!(grey(0.97), result, epsilon = 0.0001);
+    }
+
+    #[test]
+    fn smooth_metal_only_reflects_in_mirror_direction() {
+        let material = PbrMaterial::solid(Color::new(1., 0.8, 0.2), 1., 0.05);
+        let light = PointLight::new(white(), point(0., 10., -10.));
+        let s22 = Float::sqrt(2.) / 2.;
+
+        // metals have no diffuse term, so there's no light away from the highlight
+        let off_highlight = material.direct(
+            light,
+            point(0., 0., 0.),
+            point(0., 0., 0.),
+            vec(0., 0., -1.),
+            Normal3::new(0., 0., -1.),
+        );
+        assert_color_eq!(black(), off_highlight, epsilon = 0.0001);
+
+        // and the highlight itself is tinted by the base color
+        let on_highlight = material.direct(
+            light,
+            point(0., 0., 0.),
+            point(0., 0., 0.),
+            vec(0., -s22, -s22),
+            Normal3::new(0., 0., -1.),
+        );
+        assert!(on_highlight.r > 1.);
+        assert!(on_highlight.r > on_highlight.g && on_highlight.g > on_highlight.b);
+    }
+
+    #[test]
+    fn light_behind_surface() {
+        let material = PbrMaterial::default();
+        let light = PointLight::new(white(), point(0., 0., 10.));
+        let result = material.direct(
+            light,
+            point(0., 0., 0.),
+            point(0., 0., 0.),
+            vec(0., 0., -1.),
+            Normal3::new(0., 0., -1.),
+        );
+        assert_eq!(black(), result);
+    }
+
+    #[test]
+    fn pbr_objects_are_shadowed_like_phong_objects() {
+        let s1 = Sphere::unit();
+        let s2 = Sphere::pos_r_m(point(0., 0., 10.), 1., PbrMaterial::default().into());
+        let l = PointLight::new(white(), point(0., 0., -10.));
+        let w = World::new(vec![Box::new(s1), Box::new(s2)], vec![l]);
+
+        // s1 is between the light and the point on s2 that we hit, so we only see ambient light
+        let c = color_at(&w, Ray::new(point(0., 0., 5.), vec(0., 0., 1.)));
+        assert_color_eq!(Color::new(0.1, 0.1, 0.1), c);
+    }
+}
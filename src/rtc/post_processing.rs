@@ -0,0 +1,664 @@
+use crate::*;
+use std::str::FromStr;
+
+// an image-space effect applied to a rendered (linear, unclamped) framebuffer,
+// before it gets tone mapped and written out
+#[derive(Debug, Clone, PartialEq)]
+pub enum Effect {
+    // light brighter than `threshold` bleeds out into the surrounding pixels
+    Bloom {
+        threshold: Float,
+        // the standard deviation of the blur, in pixels
+        radius: Float,
+        strength: Float,
+    },
+    // darkens the image towards the corners, where 1 means the corners are black
+    Vignette {
+        strength: Float,
+    },
+    // red and blue are shifted away from each other towards the edges of the image,
+    // by up to `amount` pixels in the corners
+    ChromaticAberration {
+        amount: Float,
+    },
+    // averages each pixel with everything up to `radius` pixels away
+    BoxBlur {
+        radius: usize,
+    },
+    // a blur where nearby pixels count more, with `sigma` (in pixels) as the standard deviation
+    GaussianBlur {
+        sigma: Float,
+    },
+    // a (joint) bilateral filter: blurs each pixel only with neighbours that look
+    // similar, so noise gets smoothed out but edges stay sharp. When `Guides` are
+    // available, neighbours also need a similar normal and depth. A sigma of 0 only
+    // blends neighbours which match exactly.
+    Denoise {
+        radius: usize,
+        sigma_color: Float,
+        sigma_normal: Float,
+        sigma_depth: Float,
+    },
+}
+
+// extra information about the scene used to guide the denoiser
+#[derive(Debug, Clone, PartialEq)]
+pub struct Guides {
+    // see `Aov::Normal`
+    pub normal: Framebuffer,
+    // see `Aov::Depth`
+    pub depth: Framebuffer,
+}
+
+impl Guides {
+    // renders normal and depth passes with the same camera as the main image. Depths
+    // are scaled so the furthest thing in view is white, whatever the size of the scene.
+    pub fn render(camera: &Camera, world: &World) -> Guides {
+        let max_distance = (0..camera.vsize)
+            .flat_map(|y| (0..camera.hsize).map(move |x| (x, y)))
+            .filter_map(|(x, y)| world.closest_hit(camera.ray_for_pixel(x, y), 0., Float::INFINITY))
+            .fold(0., |max, hit| hit.t.max(max));
+
+        let mut camera = camera.clone();
+        camera.samples_per_pixel = 1;
+        let mut render_aov = |aov: Aov| {
+            camera.integrator = Integrator::Aov(aov);
+            let mut buffer = Framebuffer::new(camera.hsize, camera.vsize);
+            camera.render_to(world, &mut buffer);
+            buffer
+        };
+
+        Guides {
+            normal: render_aov(Aov::Normal),
+            depth: render_aov(Aov::Depth { max_distance }),
+        }
+    }
+}
+
+impl Effect {
+    pub fn apply(&self, image: &Framebuffer, guides: Option<&Guides>) -> Framebuffer {
+        match *self {
+            Effect::Bloom {
+                threshold,
+                radius,
+                strength,
+            } => {
+                // keep only the part of each pixel which is above the threshold
+                let bright = map_pixels(image, |x, y| {
+                    let c = image.pixel_at(x, y);
+                    let l = luminance(&c);
+                    if l > threshold {
+                        c * ((l - threshold) / l)
+                    } else {
+                        black()
+                    }
+                });
+                let glow = gaussian_blur(&bright, radius);
+                map_pixels(image, |x, y| {
+                    image.pixel_at(x, y) + glow.pixel_at(x, y) * strength
+                })
+            }
+            Effect::Vignette { strength } => {
+                let (cx, cy) = center(image);
+                let corner = (cx * cx + cy * cy).sqrt().max(1.);
+                map_pixels(image, |x, y| {
+                    let (dx, dy) = (x as Float + 0.5 - cx, y as Float + 0.5 - cy);
+                    let d = (dx * dx + dy * dy).sqrt() / corner;
+                    image.pixel_at(x, y) * (1. - strength * d * d).max(0.)
+                })
+            }
+            Effect::ChromaticAberration { amount } => {
+                let (cx, cy) = center(image);
+                let corner = (cx * cx + cy * cy).sqrt().max(1.);
+                map_pixels(image, |x, y| {
+                    let (px, py) = (x as Float + 0.5, y as Float + 0.5);
+                    // the offset grows linearly away from the center of the image
+                    let scale = amount / corner;
+                    let (ox, oy) = ((px - cx) * scale, (py - cy) * scale);
+                    let red = sample_bilinear(image, px + ox, py + oy).r;
+                    let green = image.pixel_at(x, y).g;
+                    let blue = sample_bilinear(image, px - ox, py - oy).b;
+                    Color::new(red, green, blue)
+                })
+            }
+            Effect::BoxBlur { radius } => {
+                let kernel = vec![1. / (2 * radius + 1) as Float; 2 * radius + 1];
+                separable_blur(image, &kernel)
+            }
+            Effect::GaussianBlur { sigma } => gaussian_blur(image, sigma),
+            Effect::Denoise {
+                radius,
+                sigma_color,
+                sigma_normal,
+                sigma_depth,
+            } => {
+                let r = radius as isize;
+                let sigma_spatial = (radius as Float / 2.).max(0.5);
+                // as sigma goes to 0 only exact matches get any weight, which is what
+                // we use for a sigma of 0 rather than dividing 0 by 0
+                let falloff = |d2: Float, sigma: Float| {
+                    if d2 == 0. {
+                        0.
+                    } else {
+                        d2 / (2. * sigma * sigma)
+                    }
+                };
+                map_pixels(image, |x, y| {
+                    let c = image.pixel_at(x, y);
+                    let mut total = black();
+                    let mut total_weight = 0.;
+                    for dy in -r..=r {
+                        for dx in -r..=r {
+                            let (nx, ny) = (x as isize + dx, y as isize + dy);
+                            if !contains(image, nx, ny) {
+                                continue;
+                            }
+                            let (nx, ny) = (nx as usize, ny as usize);
+                            let n = image.pixel_at(nx, ny);
+
+                            let spatial = (dx * dx + dy * dy) as Float;
+                            let mut exponent = falloff(spatial, sigma_spatial)
+                                + falloff(distance_squared(&c, &n), sigma_color);
+                            if let Some(g) = guides {
+                                let normal = distance_squared(
+                                    &g.normal.pixel_at(x, y),
+                                    &g.normal.pixel_at(nx, ny),
+                                );
+                                let depth = g.depth.pixel_at(x, y).r - g.depth.pixel_at(nx, ny).r;
+                                exponent += falloff(normal, sigma_normal)
+                                    + falloff(depth * depth, sigma_depth);
+                            }
+
+                            let weight = (-exponent).exp();
+                            total += n * weight;
+                            total_weight += weight;
+                        }
+                    }
+                    // the pixel itself always has a weight of 1, so this never divides by zero
+                    total * (1. / total_weight)
+                })
+            }
+        }
+    }
+}
+
+// parses effects like `bloom`, `blur:2` or `bloom:1.5:4:0.5`, where the
+// (optional) parameters are given in the same order as the enum fields
+impl FromStr for Effect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Effect, String> {
+        let mut parts = s.split(':');
+        let name = parts.next().unwrap_or("");
+        let params = parts
+            .map(|p| {
+                p.parse::<Float>()
+                    .map_err(|_| format!("invalid parameter {:?} for {}", p, name))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let param = |i: usize, default: Float| params.get(i).cloned().unwrap_or(default);
+
+        let (effect, max_params) = match name {
+            "bloom" => (
+                Effect::Bloom {
+                    threshold: param(0, 1.),
+                    radius: param(1, 4.),
+                    strength: param(2, 0.5),
+                },
+                3,
+            ),
+            "vignette" => (
+                Effect::Vignette {
+                    strength: param(0, 0.5),
+                },
+                1,
+            ),
+            "chromatic" => (
+                Effect::ChromaticAberration {
+                    amount: param(0, 2.),
+                },
+                1,
+            ),
+            "box" => (
+                Effect::BoxBlur {
+                    radius: param(0, 1.) as usize,
+                },
+                1,
+            ),
+            "blur" => (
+                Effect::GaussianBlur {
+                    sigma: param(0, 1.),
+                },
+                1,
+            ),
+            "denoise" => {
+                if params
+                    .iter()
+                    .skip(1)
+                    .any(|sigma| sigma.is_nan() || *sigma < 0.)
+                {
+                    return Err(format!("denoise needs sigmas of 0 or more, not {:?}", s));
+                }
+                (
+                    Effect::Denoise {
+                        radius: param(0, 3.) as usize,
+                        sigma_color: param(1, 0.2),
+                        sigma_normal: param(2, 0.1),
+                        sigma_depth: param(3, 0.02),
+                    },
+                    4,
+                )
+            }
+            _ => return Err(format!("unknown effect {:?}", name)),
+        };
+
+        if params.len() > max_params {
+            return Err(format!("too many parameters for {}", name));
+        }
+        Ok(effect)
+    }
+}
+
+// a list of effects which are applied one after the other
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PostProcess {
+    pub effects: Vec<Effect>,
+}
+
+impl PostProcess {
+    pub fn new(effects: Vec<Effect>) -> PostProcess {
+        PostProcess { effects }
+    }
+
+    pub fn needs_guides(&self) -> bool {
+        self.effects
+            .iter()
+            .any(|e| matches!(e, Effect::Denoise { .. }))
+    }
+
+    pub fn apply(&self, image: &Framebuffer, guides: Option<&Guides>) -> Framebuffer {
+        self.effects
+            .iter()
+            .fold(image.clone(), |image, effect| effect.apply(&image, guides))
+    }
+}
+
+// a comma-separated list of effects, eg `denoise,bloom:2,vignette`
+impl FromStr for PostProcess {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<PostProcess, String> {
+        let effects = s
+            .split(',')
+            .filter(|e| !e.is_empty())
+            .map(|e| e.parse())
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(PostProcess::new(effects))
+    }
+}
+
+// a copy of `image` (including its alpha values) with each pixel replaced by `f(x, y)`
+fn map_pixels<F: Fn(usize, usize) -> Color>(image: &Framebuffer, f: F) -> Framebuffer {
+    let mut result = image.clone();
+    for y in 0..image.height() {
+        for x in 0..image.width() {
+            result.write_pixel(&f(x, y), x, y);
+        }
+    }
+    result
+}
+
+fn center(image: &Framebuffer) -> (Float, Float) {
+    (image.width() as Float / 2., image.height() as Float / 2.)
+}
+
+fn contains(image: &Framebuffer, x: isize, y: isize) -> bool {
+    x >= 0 && y >= 0 && (x as usize) < image.width() && (y as usize) < image.height()
+}
+
+// the pixel nearest to (x, y), for sampling outside the edges of the image
+fn clamped_pixel_at(image: &Framebuffer, x: isize, y: isize) -> Color {
+    let x = x.clamp(0, image.width() as isize - 1) as usize;
+    let y = y.clamp(0, image.height() as isize - 1) as usize;
+    image.pixel_at(x, y)
+}
+
+// interpolates between the four pixels around a point, measured in pixels
+// from the top left corner (so pixel centers are at 0.5)
+fn sample_bilinear(image: &Framebuffer, x: Float, y: Float) -> Color {
+    let (x, y) = (x - 0.5, y - 0.5);
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let (x0, y0) = (x0 as isize, y0 as isize);
+
+    let top =
+        clamped_pixel_at(image, x0, y0) * (1. - fx) + clamped_pixel_at(image, x0 + 1, y0) * fx;
+    let bottom = clamped_pixel_at(image, x0, y0 + 1) * (1. - fx)
+        + clamped_pixel_at(image, x0 + 1, y0 + 1) * fx;
+    top * (1. - fy) + bottom * fy
+}
+
+fn gaussian_blur(image: &Framebuffer, sigma: Float) -> Framebuffer {
+    if sigma <= 0. {
+        return image.clone();
+    }
+    // anything further than 3 standard deviations away makes almost no difference
+    let radius = (sigma * 3.).ceil() as isize;
+    let kernel = (-radius..=radius)
+        .map(|i| (-(i * i) as Float / (2. * sigma * sigma)).exp())
+        .collect::<Vec<_>>();
+    let total: Float = kernel.iter().sum();
+    let kernel = kernel.iter().map(|k| k / total).collect::<Vec<_>>();
+    separable_blur(image, &kernel)
+}
+
+// blurs horizontally and then vertically with the same (odd-sized) kernel,
+// which is equivalent to (but much faster than) a full 2d blur
+fn separable_blur(image: &Framebuffer, kernel: &[Float]) -> Framebuffer {
+    let radius = (kernel.len() / 2) as isize;
+    let horizontal = map_pixels(image, |x, y| {
+        let mut total = black();
+        for (i, k) in kernel.iter().enumerate() {
+            total += clamped_pixel_at(image, x as isize + i as isize - radius, y as isize) * *k;
+        }
+        total
+    });
+    map_pixels(&horizontal, |x, y| {
+        let mut total = black();
+        for (i, k) in kernel.iter().enumerate() {
+            total +=
+                clamped_pixel_at(&horizontal, x as isize, y as isize + i as isize - radius) * *k;
+        }
+        total
+    })
+}
+
+// how bright a color looks, using the Rec. 709 weights
+fn luminance(c: &Color) -> Float {
+    0.2126 * c.r + 0.7152 * c.g + 0.0722 * c.b
+}
+
+fn distance_squared(a: &Color, b: &Color) -> Float {
+    let d = a - b;
+    d.r * d.r + d.g * d.g + d.b * d.b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image_from(width: usize, height: usize, f: impl Fn(usize, usize) -> Color) -> Framebuffer {
+        map_pixels(&Framebuffer::new(width, height), f)
+    }
+
+    fn total(image: &Framebuffer) -> Color {
+        let mut total = black();
+        for (_, _, c) in image.pixels() {
+            total += c;
+        }
+        total
+    }
+
+    #[test]
+    fn blurs_keep_flat_images_unchanged() {
+        let image = image_from(5, 4, |_, _| grey(0.3));
+        for effect in &[
+            Effect::BoxBlur { radius: 2 },
+            Effect::GaussianBlur { sigma: 1.5 },
+        ] {
+            let result = effect.apply(&image, None);
+            for (x, y, c) in result.pixels() {
+                assert_color_eq!(image.pixel_at(x, y), c, epsilon = 0.00001);
+            }
+        }
+    }
+
+    #[test]
+    fn box_blur_averages_neighbours() {
+        let mut image = Framebuffer::new(5, 5);
+        image.write_pixel(&grey(9.), 2, 2);
+
+        let result = Effect::BoxBlur { radius: 1 }.apply(&image, None);
+        assert_color_eq!(grey(1.), result.pixel_at(1, 1), epsilon = 0.00001);
+        assert_color_eq!(grey(1.), result.pixel_at(2, 2), epsilon = 0.00001);
+        assert_eq!(black(), result.pixel_at(0, 0));
+    }
+
+    #[test]
+    fn gaussian_blur_spreads_light_without_losing_any() {
+        let mut image = Framebuffer::new(21, 21);
+        image.write_pixel(&white(), 10, 10);
+
+        let result = Effect::GaussianBlur { sigma: 2. }.apply(&image, None);
+        assert_color_eq!(white(), total(&result), epsilon = 0.0001);
+        // the center is still the brightest, and it falls off symmetrically
+        assert!(result.pixel_at(10, 10).r > result.pixel_at(11, 10).r);
+        assert!(result.pixel_at(11, 10).r > result.pixel_at(12, 10).r);
+        assert_eq!(result.pixel_at(8, 10), result.pixel_at(12, 10));
+        assert_eq!(result.pixel_at(10, 8), result.pixel_at(10, 12));
+    }
+
+    #[test]
+    fn bloom_only_spreads_bright_pixels() {
+        let mut image = image_from(21, 21, |_, _| grey(0.5));
+        image.write_pixel(&grey(10.), 10, 10);
+
+        let bloom = Effect::Bloom {
+            threshold: 1.,
+            radius: 2.,
+            strength: 1.,
+        };
+        let result = bloom.apply(&image, None);
+        // pixels around the bright one get brighter, but far away ones don't change
+        assert!(result.pixel_at(11, 10).r > 0.5);
+        assert_color_eq!(grey(0.5), result.pixel_at(0, 0), epsilon = 0.00001);
+    }
+
+    #[test]
+    fn vignette_darkens_corners_but_not_the_center() {
+        let image = image_from(11, 11, |_, _| white());
+        let result = Effect::Vignette { strength: 1. }.apply(&image, None);
+
+        assert_color_eq!(white(), result.pixel_at(5, 5), epsilon = 0.00001);
+        assert!(result.pixel_at(0, 0).r < 0.2);
+        assert!(result.pixel_at(0, 5).r > result.pixel_at(0, 0).r);
+    }
+
+    #[test]
+    fn chromatic_aberration_separates_red_and_blue_towards_the_edges() {
+        // a single white column, to the right of center
+        let image = image_from(21, 1, |x, _| if x == 15 { white() } else { black() });
+        let result = Effect::ChromaticAberration { amount: 2. }.apply(&image, None);
+
+        // green doesn't move, but red is sampled from further out, so it appears
+        // further in, and the opposite happens for blue
+        assert_eq!(1., result.pixel_at(15, 0).g);
+        assert!(result.pixel_at(15, 0).r < 0.5);
+        assert!(result.pixel_at(14, 0).r > 0.5);
+        assert_eq!(0., result.pixel_at(14, 0).b);
+        assert!(result.pixel_at(16, 0).b > 0.5);
+        assert_eq!(0., result.pixel_at(16, 0).r);
+    }
+
+    #[test]
+    fn denoise_smooths_noise_but_keeps_edges() {
+        // a noisy left half which is dark, and a right half which is bright
+        let image = image_from(20, 10, |x, y| {
+            let base = if x < 10 { 0.2 } else { 0.8 };
+            let noise = Rng::for_pixel(0, x, y).next_float() - 0.5;
+            grey(base + noise * 0.1)
+        });
+
+        let denoise = Effect::Denoise {
+            radius: 3,
+            sigma_color: 0.1,
+            sigma_normal: 0.1,
+            sigma_depth: 0.1,
+        };
+        let result = denoise.apply(&image, None);
+
+        // the edge is still sharp
+        assert!(result.pixel_at(9, 5).r < 0.3);
+        assert!(result.pixel_at(10, 5).r > 0.7);
+        // and the noise is smaller than before
+        let variation = |image: &Framebuffer| {
+            (1..9)
+                .map(|x| (image.pixel_at(x, 5).r - image.pixel_at(x - 1, 5).r).abs())
+                .sum::<Float>()
+        };
+        assert!(variation(&result) < variation(&image) / 2.);
+    }
+
+    #[test]
+    fn denoise_uses_guides_to_find_edges() {
+        // the image is a flat color, but the guides say there are two objects
+        let image = image_from(10, 1, |x, _| if x == 4 { white() } else { grey(0.5) });
+        let guides = Guides {
+            normal: image_from(10, 1, |x, _| {
+                if x < 5 {
+                    Color::new(0.5, 1., 0.5)
+                } else {
+                    Color::new(1., 0.5, 0.5)
+                }
+            }),
+            depth: image_from(10, 1, |_, _| grey(0.5)),
+        };
+
+        let denoise = Effect::Denoise {
+            radius: 2,
+            sigma_color: 1.,
+            sigma_normal: 0.1,
+            sigma_depth: 0.1,
+        };
+        let unguided = denoise.apply(&image, None);
+        let guided = denoise.apply(&image, Some(&guides));
+
+        // without guides the bright pixel bleeds across, but with guides it stays on its own object
+        assert!(unguided.pixel_at(5, 0).r > 0.55);
+        assert_color_eq!(grey(0.5), guided.pixel_at(5, 0), epsilon = 0.0001);
+    }
+
+    #[test]
+    fn denoise_with_a_sigma_of_zero_only_blends_exact_matches() {
+        let image = image_from(5, 1, |x, _| if x == 2 { white() } else { grey(0.5) });
+        let denoise = Effect::Denoise {
+            radius: 2,
+            sigma_color: 0.,
+            sigma_normal: 0.,
+            sigma_depth: 0.,
+        };
+        let result = denoise.apply(&image, None);
+
+        for x in 0..5 {
+            assert_eq!(image.pixel_at(x, 0), result.pixel_at(x, 0));
+        }
+    }
+
+    #[test]
+    fn effects_keep_alpha() {
+        let mut image = Framebuffer::new(3, 3);
+        image.write_alpha(0., 1, 1);
+        let result = Effect::GaussianBlur { sigma: 1. }.apply(&image, None);
+        assert_eq!(0., result.alpha_at(1, 1));
+        assert_eq!(1., result.alpha_at(0, 0));
+    }
+
+    #[test]
+    fn effects_are_applied_in_order() {
+        let mut image = Framebuffer::new(3, 1);
+        image.write_pixel(&grey(3.), 1, 0);
+
+        let blur_then_bloom: PostProcess = "box:1,bloom:0.5:1:1".parse().unwrap();
+        let bloom_then_blur: PostProcess = "bloom:0.5:1:1,box:1".parse().unwrap();
+        let a = blur_then_bloom.apply(&image, None);
+        let b = bloom_then_blur.apply(&image, None);
+        assert!(a != b);
+        assert_eq!(
+            Effect::BoxBlur { radius: 1 }.apply(
+                &Effect::Bloom {
+                    threshold: 0.5,
+                    radius: 1.,
+                    strength: 1.
+                }
+                .apply(&image, None),
+                None
+            ),
+            b
+        );
+    }
+
+    #[test]
+    fn parsing_effects() {
+        assert_eq!(
+            Ok(Effect::Bloom {
+                threshold: 1.5,
+                radius: 4.,
+                strength: 0.5
+            }),
+            "bloom:1.5".parse()
+        );
+        assert_eq!(Ok(Effect::BoxBlur { radius: 2 }), "box:2".parse());
+        assert_eq!(
+            Ok(Effect::ChromaticAberration { amount: 2. }),
+            "chromatic".parse()
+        );
+        assert!("blur:1:2".parse::<Effect>().is_err());
+        assert!("blur:x".parse::<Effect>().is_err());
+        assert!("sharpen".parse::<Effect>().is_err());
+        assert_eq!(
+            Ok(Effect::Denoise {
+                radius: 3,
+                sigma_color: 0.,
+                sigma_normal: 0.1,
+                sigma_depth: 0.02
+            }),
+            "denoise:3:0".parse()
+        );
+        assert!("denoise:3:0.2:0.1:-1".parse::<Effect>().is_err());
+        assert!("denoise:3:NaN".parse::<Effect>().is_err());
+
+        let post: PostProcess = "denoise,vignette:0.3".parse().unwrap();
+        assert_eq!(2, post.effects.len());
+        assert!(post.needs_guides());
+        assert_eq!(Effect::Vignette { strength: 0.3 }, post.effects[1]);
+    }
+
+    #[test]
+    fn guides_come_from_aov_passes() {
+        let guides_at_scale = |scale: Float| {
+            let w = World::new(
+                vec![Box::new(Sphere::new(
+                    scaling(scale, scale, scale),
+                    Material::default(),
+                ))],
+                vec![],
+            );
+            let c = Camera::new(
+                11,
+                11,
+                crate::consts::PI / 2.,
+                view_transform(
+                    point(0., 0., -5. * scale),
+                    point(0., 0., 0.),
+                    vec(0., 1., 0.),
+                ),
+            );
+            Guides::render(&c, &w)
+        };
+        let guides = guides_at_scale(1.);
+
+        // the center of the image looks straight at the front of the sphere
+        assert_color_eq!(
+            Color::new(0.5, 0.5, 0.),
+            guides.normal.pixel_at(5, 5),
+            epsilon = 0.0001
+        );
+        let center = guides.depth.pixel_at(5, 5);
+        assert!(center.r > 0.5 && center.r < 1.);
+        assert_eq!(white(), guides.depth.pixel_at(0, 0));
+
+        // depths are relative to the scene, so a bigger one looks the same
+        let bigger = guides_at_scale(100.);
+        assert_color_eq!(center, bigger.depth.pixel_at(5, 5), epsilon = 0.0001);
+    }
+}
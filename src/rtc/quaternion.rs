@@ -0,0 +1,262 @@
+use crate::*;
+use std::ops::Mul;
+
+// a rotation, stored as a unit quaternion. Unlike a rotation matrix (or euler angles)
+// these can be interpolated smoothly, see `slerp`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quaternion {
+    pub w: Float,
+    pub x: Float,
+    pub y: Float,
+    pub z: Float,
+}
+
+impl Quaternion {
+    pub fn new(w: Float, x: Float, y: Float, z: Float) -> Quaternion {
+        Quaternion { w, x, y, z }
+    }
+
+    pub fn identity() -> Quaternion {
+        Quaternion::new(1., 0., 0., 0.)
+    }
+
+    // a rotation of `rad` radians around `axis`, anticlockwise when looking down the axis
+    // towards the origin (the same direction as `rotation_x` and friends)
+    pub fn from_axis_angle(axis: Vector3, rad: Float) -> Quaternion {
+        let axis = axis.normalize();
+        let (sin, cos) = (rad / 2.).sin_cos();
+        Quaternion::new(cos, axis.x * sin, axis.y * sin, axis.z * sin)
+    }
+
+    // the rotation part of a matrix. The upper 3x3 part needs to be a pure rotation,
+    // ie without any scaling or shearing (see `decompose` for matrices which have them).
+    pub fn from_matrix(m: &Matrix4) -> Quaternion {
+        // see "Converting a Rotation Matrix to a Quaternion" by Mike Day. The largest
+        // of w, x, y or z is found first, since dividing by it is the most accurate.
+        let trace = m[(0, 0)] + m[(1, 1)] + m[(2, 2)];
+        let q = if trace > 0. {
+            let s = (trace + 1.).sqrt() * 2.;
+            Quaternion::new(
+                s / 4.,
+                (m[(2, 1)] - m[(1, 2)]) / s,
+                (m[(0, 2)] - m[(2, 0)]) / s,
+                (m[(1, 0)] - m[(0, 1)]) / s,
+            )
+        } else if m[(0, 0)] > m[(1, 1)] && m[(0, 0)] > m[(2, 2)] {
+            let s = (1. + m[(0, 0)] - m[(1, 1)] - m[(2, 2)]).sqrt() * 2.;
+            Quaternion::new(
+                (m[(2, 1)] - m[(1, 2)]) / s,
+                s / 4.,
+                (m[(0, 1)] + m[(1, 0)]) / s,
+                (m[(0, 2)] + m[(2, 0)]) / s,
+            )
+        } else if m[(1, 1)] > m[(2, 2)] {
+            let s = (1. + m[(1, 1)] - m[(0, 0)] - m[(2, 2)]).sqrt() * 2.;
+            Quaternion::new(
+                (m[(0, 2)] - m[(2, 0)]) / s,
+                (m[(0, 1)] + m[(1, 0)]) / s,
+                s / 4.,
+                (m[(1, 2)] + m[(2, 1)]) / s,
+            )
+        } else {
+            let s = (1. + m[(2, 2)] - m[(0, 0)] - m[(1, 1)]).sqrt() * 2.;
+            Quaternion::new(
+                (m[(1, 0)] - m[(0, 1)]) / s,
+                (m[(0, 2)] + m[(2, 0)]) / s,
+                (m[(1, 2)] + m[(2, 1)]) / s,
+                s / 4.,
+            )
+        };
+        q.normalize()
+    }
+
+    #[rustfmt::skip]
+    pub fn to_matrix(&self) -> Matrix4 {
+        let Quaternion { w, x, y, z } = self.normalize();
+        Matrix4::new(
+            1. - 2. * (y * y + z * z), 2. * (x * y - w * z),      2. * (x * z + w * y),      0.,
+            2. * (x * y + w * z),      1. - 2. * (x * x + z * z), 2. * (y * z - w * x),      0.,
+            2. * (x * z - w * y),      2. * (y * z + w * x),      1. - 2. * (x * x + y * y), 0.,
+            0.,                        0.,                        0.,                        1.,
+        )
+    }
+
+    pub fn dot(&self, other: &Quaternion) -> Float {
+        self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn normalize(&self) -> Quaternion {
+        let length = self.dot(self).sqrt();
+        Quaternion::new(
+            self.w / length,
+            self.x / length,
+            self.y / length,
+            self.z / length,
+        )
+    }
+
+    // the opposite rotation
+    pub fn conjugate(&self) -> Quaternion {
+        Quaternion::new(self.w, -self.x, -self.y, -self.z)
+    }
+
+    pub fn rotate(&self, v: Vector3) -> Vector3 {
+        let q = Quaternion::new(0., v.x, v.y, v.z);
+        let r = *self * q * self.conjugate();
+        vec(r.x, r.y, r.z)
+    }
+
+    // spherical linear interpolation: turns at a constant speed from `self` (t = 0)
+    // to `other` (t = 1), taking the shortest way around
+    pub fn slerp(&self, other: &Quaternion, t: Float) -> Quaternion {
+        // q and -q are the same rotation, but only one of them is the short way round
+        let mut cos = self.dot(other);
+        let other = if cos < 0. {
+            cos = -cos;
+            Quaternion::new(-other.w, -other.x, -other.y, -other.z)
+        } else {
+            *other
+        };
+
+        let (a, b) = if cos > 0.9995 {
+            // nearly the same rotation, where sin(angle) gets too small to divide by
+            (1. - t, t)
+        } else {
+            let angle = cos.acos();
+            let sin = angle.sin();
+            (((1. - t) * angle).sin() / sin, (t * angle).sin() / sin)
+        };
+
+        Quaternion::new(
+            a * self.w + b * other.w,
+            a * self.x + b * other.x,
+            a * self.y + b * other.y,
+            a * self.z + b * other.z,
+        )
+        .normalize()
+    }
+}
+
+// combining rotations, like multiplying matrices: `a * b` rotates by `b` and then by `a`
+impl Mul for Quaternion {
+    type Output = Quaternion;
+
+    fn mul(self, o: Quaternion) -> Quaternion {
+        Quaternion::new(
+            self.w * o.w - self.x * o.x - self.y * o.y - self.z * o.z,
+            self.w * o.x + self.x * o.w + self.y * o.z - self.z * o.y,
+            self.w * o.y - self.x * o.z + self.y * o.w + self.z * o.x,
+            self.w * o.z + self.x * o.y - self.y * o.x + self.z * o.w,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consts::PI;
+    extern crate approx;
+    use self::approx::assert_relative_eq;
+
+    #[test]
+    fn axis_angle_rotations_match_the_axis_rotations() {
+        let angle = 0.7;
+        assert_relative_eq!(
+            rotation_x(angle),
+            Quaternion::from_axis_angle(vec(1., 0., 0.), angle).to_matrix(),
+            epsilon = 0.00001
+        );
+        assert_relative_eq!(
+            rotation_y(angle),
+            Quaternion::from_axis_angle(vec(0., 2., 0.), angle).to_matrix(),
+            epsilon = 0.00001
+        );
+        assert_relative_eq!(
+            rotation_z(angle),
+            Quaternion::from_axis_angle(vec(0., 0., 1.), angle).to_matrix(),
+            epsilon = 0.00001
+        );
+        assert_eq!(Matrix4::identity(), Quaternion::identity().to_matrix());
+    }
+
+    #[test]
+    fn rotating_vectors() {
+        let q = Quaternion::from_axis_angle(vec(1., 1., 1.), 2. * PI / 3.);
+        // a third of a turn around the diagonal moves each axis onto the next one
+        assert_tuple_eq!(
+            vec(0., 1., 0.),
+            q.rotate(vec(1., 0., 0.)),
+            epsilon = 0.00001
+        );
+        assert_tuple_eq!(
+            q.to_matrix() * vec(1., 2., 3.),
+            q.rotate(vec(1., 2., 3.)),
+            epsilon = 0.00001
+        );
+        assert_tuple_eq!(
+            vec(1., 2., 3.),
+            q.conjugate().rotate(q.rotate(vec(1., 2., 3.))),
+            epsilon = 0.00001
+        );
+    }
+
+    #[test]
+    fn combining_rotations_is_like_multiplying_matrices() {
+        let a = Quaternion::from_axis_angle(vec(0., 1., 0.), 0.5);
+        let b = Quaternion::from_axis_angle(vec(1., 0., 2.), -1.2);
+        assert_relative_eq!(
+            a.to_matrix() * b.to_matrix(),
+            (a * b).to_matrix(),
+            epsilon = 0.00001
+        );
+    }
+
+    #[test]
+    fn converting_from_matrices() {
+        // including rotations of nearly half a turn, which take the other branches
+        let rotations = [
+            rotation_x(0.3) * rotation_y(-1.1),
+            rotation_x(3.),
+            rotation_y(3.1),
+            rotation_z(-3.),
+            rotation_z(PI / 2.) * rotation_x(PI),
+        ];
+        for m in rotations.iter() {
+            assert_relative_eq!(
+                *m,
+                Quaternion::from_matrix(m).to_matrix(),
+                epsilon = 0.00001
+            );
+        }
+    }
+
+    #[test]
+    fn slerp_turns_at_a_constant_speed() {
+        let a = Quaternion::identity();
+        let b = Quaternion::from_axis_angle(vec(0., 1., 0.), PI / 2.);
+
+        assert_relative_eq!(
+            a.to_matrix(),
+            a.slerp(&b, 0.).to_matrix(),
+            epsilon = 0.00001
+        );
+        assert_relative_eq!(
+            b.to_matrix(),
+            a.slerp(&b, 1.).to_matrix(),
+            epsilon = 0.00001
+        );
+        assert_relative_eq!(
+            rotation_y(PI / 8.),
+            a.slerp(&b, 0.25).to_matrix(),
+            epsilon = 0.00001
+        );
+
+        // -b is the same rotation, so we still go the short way round
+        let minus_b = Quaternion::new(-b.w, -b.x, -b.y, -b.z);
+        assert_relative_eq!(
+            rotation_y(PI / 4.),
+            a.slerp(&minus_b, 0.5).to_matrix(),
+            epsilon = 0.00001
+        );
+    }
+}
@@ -1,56 +1,94 @@
 use crate::*;
 use std::ops::Mul;
 extern crate float_cmp;
-use self::float_cmp::{ApproxEq, F32Margin};
+use self::float_cmp::ApproxEq;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Ray {
-    pub origin: Tuple,
-    pub direction: Tuple,
+    pub origin: Point3,
+    pub direction: Vector3,
+    // when the ray was cast, from 0 when the camera's shutter opens to 1 when it closes.
+    // moving objects and cameras are in different places at different times
+    pub time: Float,
 }
 
 impl Ray {
-    pub fn new(origin: Tuple, direction: Tuple) -> Ray {
-        assert!(origin.is_point());
-        assert!(direction.is_vec());
-        Ray { origin, direction }
+    pub fn new(origin: Point3, direction: Vector3) -> Ray {
+        Ray {
+            origin,
+            direction,
+            time: 0.,
+        }
     }
 
-    pub fn position(&self, t: f32) -> Tuple {
+    pub fn with_time(self, time: Float) -> Ray {
+        Ray { time, ..self }
+    }
+
+    pub fn position(&self, t: Float) -> Point3 {
         &self.origin + &self.direction * t
     }
 }
 
 /// based on https://docs.rs/float-cmp/0.6.0/float_cmp/index.html
 impl ApproxEq for Ray {
-    type Margin = F32Margin;
+    type Margin = FloatMargin;
 
     fn approx_eq<T: Into<Self::Margin>>(self, other: Self, margin: T) -> bool {
         let margin = margin.into();
         self.origin.approx_eq(other.origin, margin)
             && self.direction.approx_eq(other.direction, margin)
+            && self.time.approx_eq(other.time, margin)
     }
 }
 
 // do a ray intersection against an object in world space
 pub trait RayIntersection {
-    fn ray_intersection(&self, ray: Ray) -> Vec<Intersection>;
+    // adds every intersection onto the end of `hits`. Reusing the same buffer
+    // for lots of rays means we don't need to allocate for each of them.
+    fn intersect_into<'a>(&'a self, ray: Ray, hits: &mut Vec<Intersection<'a>>);
+
+    // the intersection with the smallest t value in [tmin, tmax], if there is one
+    fn closest_hit(&self, ray: Ray, tmin: Float, tmax: Float) -> Option<Intersection<'_>>;
+
+    // every intersection, in a new Vec
+    fn ray_intersection(&self, ray: Ray) -> Vec<Intersection<'_>> {
+        let mut hits = vec![];
+        self.intersect_into(ray, &mut hits);
+        hits
+    }
 }
 
 // do a ray intersection against an object in object space
 pub trait LocalRayIntersection {
-    fn local_ray_intersection(&self, ray: Ray) -> Vec<Intersection>;
+    fn local_intersections(&self, ray: Ray) -> LocalHits;
 }
 
 // if we have an object space ray intersection implementation, we can
 // implement one in world space by transforming the ray into local object space
 impl<T: LocalRayIntersection + Shape> RayIntersection for T {
-    fn ray_intersection(&self, ray: Ray) -> Vec<Intersection> {
+    fn intersect_into<'a>(&'a self, ray: Ray, hits: &mut Vec<Intersection<'a>>) {
         // we use the inverse of the objects's transformation to move the ray
         // into the object's local (object) space, then do a local ray intersection
-        let local_ray = self.world_to_object() * ray;
+        let local_ray = self.world_to_object_at_time(ray.time) * ray;
+
+        for t in self.local_intersections(local_ray).as_slice() {
+            hits.push(Intersection { t: *t, obj: self });
+        }
+    }
 
-        self.local_ray_intersection(local_ray)
+    fn closest_hit(&self, ray: Ray, tmin: Float, tmax: Float) -> Option<Intersection<'_>> {
+        let local_ray = self.world_to_object_at_time(ray.time) * ray;
+
+        self.local_intersections(local_ray)
+            .as_slice()
+            .iter()
+            .filter(|t| **t >= tmin && **t <= tmax)
+            .fold(None, |closest: Option<Float>, t| match closest {
+                Some(c) if c <= *t => Some(c),
+                _ => Some(*t),
+            })
+            .map(|t| Intersection { t, obj: self })
     }
 }
 
@@ -58,13 +96,11 @@ impl Mul<Ray> for Matrix4 {
     type Output = Ray;
 
     fn mul(self, other: Ray) -> Ray {
-        Ray::new(self * other.origin, self * other.direction)
+        Ray::new(self * other.origin, self * other.direction).with_time(other.time)
     }
 }
 
-pub fn reflect(vector: Tuple, normal: Tuple) -> Tuple {
-    assert!(vector.is_vec());
-    assert!(normal.is_vec());
+pub fn reflect(vector: Vector3, normal: Normal3) -> Vector3 {
     vector - normal * 2. * vector.dot(normal)
 }
 
@@ -104,6 +140,14 @@ pub mod tests {
         assert_eq!(vec(0., 1., 0.), r2.direction);
     }
 
+    #[test]
+    fn transforming_a_ray_keeps_its_time() {
+        let r = Ray::new(point(1., 2., 3.), vec(0., 1., 0.)).with_time(0.25);
+        let r2 = translation(3., 4., 5.) * r;
+
+        assert_eq!(0.25, r2.time);
+    }
+
     #[test]
     fn scaling_a_ray() {
         let r = Ray::new(point(1., 2., 3.), vec(0., 1., 0.));
@@ -117,12 +161,12 @@ pub mod tests {
     #[test]
     fn reflecting_a_vector() {
         let v = vec(1., -1., 0.);
-        let n = vec(0., 1., 0.);
+        let n = Normal3::new(0., 1., 0.);
         assert_tuple_eq!(vec(1., 1., 0.), reflect(v, n));
 
         let v2 = vec(0., -1., 0.);
-        let s22 = 2_f32.sqrt() / 2.;
-        let n2 = vec(s22, s22, 0.);
+        let s22 = Float::sqrt(2.) / 2.;
+        let n2 = Normal3::new(s22, s22, 0.);
         assert_tuple_eq!(vec(1., 0., 0.), reflect(v2, n2));
     }
 }
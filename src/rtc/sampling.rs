@@ -0,0 +1,143 @@
+use crate::consts::PI;
+use crate::*;
+
+// a small, fast, seedable random number generator (xorshift64*).
+// we don't need anything cryptographically strong here, just something
+// cheap that gives the same image every time for the same seed.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // xorshift gets stuck if its state is ever zero
+        Rng {
+            state: splitmix64(seed) | 1,
+        }
+    }
+
+    // a separate generator for each pixel, so that the result for one
+    // pixel doesn't depend on the order that the pixels are rendered in
+    pub fn for_pixel(seed: u64, x: usize, y: usize) -> Rng {
+        Rng::new(seed ^ splitmix64(((y as u64) << 32) | x as u64))
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+        (x.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 32) as u32
+    }
+
+    // a uniformly distributed value in [0, 1)
+    pub fn next_float(&mut self) -> Float {
+        // f32 only has 24 bits of precision, so only use the top 24 bits (which is
+        // still plenty for sampling when building with f64)
+        (self.next_u32() >> 8) as Float / (1_u32 << 24) as Float
+    }
+}
+
+// used to scramble seeds, so that similar seeds give very different sequences
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+// pick a random direction in the hemisphere around `normal`, where directions
+// closer to the normal are more likely (with probability proportional to cos(theta)).
+// this matches the distribution of light reflected from a perfectly diffuse surface.
+pub fn cosine_sample_hemisphere(normal: Normal3, rng: &mut Rng) -> Vector3 {
+    // pick a uniform point on the unit disk, then project it up onto the hemisphere
+    let u1 = rng.next_float();
+    let u2 = rng.next_float();
+    let r = u1.sqrt();
+    let phi = 2. * PI * u2;
+
+    let (tangent, bitangent) = orthonormal_basis(normal);
+    let up = (1. - u1).max(0.).sqrt();
+    (tangent * (r * phi.cos()) + bitangent * (r * phi.sin()) + normal * up).normalize()
+}
+
+// two vectors which are perpendicular to each other and `normal`
+fn orthonormal_basis(normal: Normal3) -> (Vector3, Vector3) {
+    // any vector which isn't parallel to the normal will do to get started
+    let normal = Vector3::from(normal);
+    let other = if normal.x.abs() > 0.9 {
+        vec(0., 1., 0.)
+    } else {
+        vec(1., 0., 0.)
+    };
+    let tangent = normal.cross(&other).normalize();
+    let bitangent = normal.cross(&tangent);
+    (tangent, bitangent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rng_is_deterministic_for_a_given_seed() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        let mut c = Rng::new(43);
+        let xs = (0..10).map(|_| a.next_u32()).collect::<Vec<_>>();
+        let ys = (0..10).map(|_| b.next_u32()).collect::<Vec<_>>();
+        let zs = (0..10).map(|_| c.next_u32()).collect::<Vec<_>>();
+        assert_eq!(xs, ys);
+        assert_ne!(xs, zs);
+    }
+
+    #[test]
+    fn rng_floats_are_uniform_in_unit_interval() {
+        let mut rng = Rng::new(0);
+        let n = 10000;
+        let mut total = 0.;
+        for _ in 0..n {
+            let x = rng.next_float();
+            assert!((0. ..1.).contains(&x));
+            total += x;
+        }
+        let mean = total / n as Float;
+        assert!((mean - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn neighbouring_pixels_get_different_sequences() {
+        let mut a = Rng::for_pixel(1, 10, 20);
+        let mut b = Rng::for_pixel(1, 11, 20);
+        let mut c = Rng::for_pixel(1, 10, 21);
+        let x = a.next_u32();
+        assert_ne!(x, b.next_u32());
+        assert_ne!(x, c.next_u32());
+    }
+
+    #[test]
+    fn cosine_samples_are_unit_vectors_in_the_normals_hemisphere() {
+        let mut rng = Rng::new(7);
+        let s33 = Float::sqrt(3.) / 3.;
+        for normal in &[
+            Normal3::new(0., 1., 0.),
+            Normal3::new(1., 0., 0.),
+            Normal3::new(-s33, s33, -s33),
+        ] {
+            let n = 2000;
+            let mut total_cos = 0.;
+            for _ in 0..n {
+                let d = cosine_sample_hemisphere(*normal, &mut rng);
+                assert!((d.magnitude() - 1.).abs() < 0.0001);
+                let cos = d.dot(*normal);
+                assert!(cos >= 0.);
+                total_cos += cos;
+            }
+            // the mean of cos(theta) under a cosine-weighted distribution is 2/3
+            let mean_cos = total_cos / n as Float;
+            assert!((mean_cos - 2. / 3.).abs() < 0.02, "{}", mean_cos);
+        }
+    }
+}
@@ -4,19 +4,144 @@ mod sphere;
 pub use self::sphere::*;
 mod plane;
 pub use self::plane::*;
+mod disk;
+pub use self::disk::*;
+mod rectangle;
+pub use self::rectangle::*;
 
 pub trait Shape: std::fmt::Debug {
+    // the Shape's local object space -> world space transformation
+    fn transform(&self) -> &CachedTransform;
     // transformation matrix for world space -> Shape's local object space
-    fn world_to_object(&self) -> Matrix4;
-    fn material(&self) -> &PhongMaterial;
-    fn local_normal_at(&self, point: Tuple) -> Tuple;
-    fn normal_at(&self, point: Tuple) -> Tuple {
-        let world_to_object = self.world_to_object();
+    fn world_to_object(&self) -> Matrix4 {
+        self.transform().inverse()
+    }
+    // replaces the object -> world transformation, eg to move the shape between animation frames
+    fn try_set_transform(&mut self, transformation: Matrix4) -> Result<(), RtcError>;
+    fn set_transform(&mut self, transformation: Matrix4) {
+        self.try_set_transform(transformation)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+    // how the shape moves while the camera's shutter is open, if it does at all
+    fn motion(&self) -> Option<&Motion>;
+    fn set_motion(&mut self, motion: Option<Motion>);
+    // the world -> object transformation for a ray cast at `time` (see `Ray::time`)
+    fn world_to_object_at_time(&self, time: Float) -> Matrix4 {
+        match self.motion() {
+            Some(motion) => motion.world_to_object_at_time(time),
+            None => self.world_to_object(),
+        }
+    }
+    fn material(&self) -> &Material;
+    fn local_normal_at(&self, point: Point3) -> Normal3;
+    // texture coordinates for a point on the shape's surface, in object space
+    fn local_uv(&self, point: Point3) -> (Float, Float);
+    // a box around the shape in object space, used to skip rays which can't hit it
+    fn local_bounds(&self) -> BoundingBox {
+        BoundingBox::infinite()
+    }
+    fn normal_at(&self, point: Point3) -> Normal3 {
+        self.normal_at_time(point, 0.)
+    }
+    fn normal_at_time(&self, point: Point3, time: Float) -> Normal3 {
+        let world_to_object = self.world_to_object_at_time(time);
         let object_point = world_to_object * point;
         let object_normal = self.local_normal_at(object_point);
         // https://computergraphics.stackexchange.com/a/1506 for `transpose()` justification
-        let mut world_normal = world_to_object.transpose() * object_normal;
-        world_normal.w = 0.;
+        let world_normal = match self.motion() {
+            Some(_) => object_normal.transform_by_inverse(&world_to_object),
+            None => {
+                object_normal.transform_by_inverse_transpose(&self.transform().inverse_transpose())
+            }
+        };
         return world_normal.normalize();
     }
 }
+
+// a shape moving between two placements over the shutter interval. While a
+// shape has a motion, it replaces the shape's usual transformation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Motion {
+    // object -> world transformations when the shutter opens and closes
+    transforms: TransformBlend,
+}
+
+impl Motion {
+    pub fn new(start: impl Into<Matrix4>, end: impl Into<Matrix4>) -> Motion {
+        Motion::try_new(start, end).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_new(start: impl Into<Matrix4>, end: impl Into<Matrix4>) -> Result<Motion, RtcError> {
+        Ok(Motion {
+            transforms: TransformBlend::new(
+                CachedTransform::for_object(start, "Motion start")?,
+                CachedTransform::for_object(end, "Motion end")?,
+            ),
+        })
+    }
+
+    pub fn world_to_object_at_time(&self, time: Float) -> Matrix4 {
+        // rotations turn in an arc, rather than cutting across in a straight line
+        self.transforms.inverse_at(time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moving_shapes_are_placed_by_time() {
+        let mut s = Sphere::unit();
+        s.set_motion(Some(Motion::new(
+            translation(0., 0., 0.),
+            translation(2., 0., 0.),
+        )));
+
+        assert_eq!(Matrix4::identity(), s.world_to_object_at_time(0.));
+        assert_eq!(translation(-1., 0., 0.), s.world_to_object_at_time(0.5));
+        assert_eq!(translation(-2., 0., 0.), s.world_to_object_at_time(1.));
+        // the shape stays still outside the shutter interval
+        assert_eq!(translation(-2., 0., 0.), s.world_to_object_at_time(2.));
+
+        assert_tuple_eq!(
+            Normal3::new(0., 0., -1.),
+            s.normal_at_time(point(1., 0., -1.), 0.5)
+        );
+    }
+
+    #[test]
+    fn turning_shapes_keep_their_size() {
+        let mut s = Sphere::unit();
+        s.set_motion(Some(Motion::new(
+            Matrix4::identity(),
+            rotation_z(crate::consts::PI * 0.9),
+        )));
+
+        // half way through, the sphere has turned by 81 degrees rather than being squashed
+        let p = s.world_to_object_at_time(0.5).try_inverse().unwrap() * point(1., 0., 0.);
+        let angle = crate::consts::PI * 0.45;
+        assert_tuple_eq!(point(angle.cos(), angle.sin(), 0.), p, epsilon = 0.0001);
+    }
+
+    #[test]
+    fn motions_never_panic_part_way_through() {
+        // flipping over from a mirror image passes through a flat scale half way
+        let flip = Motion::new(scaling(-1., 1., 1.), Matrix4::identity());
+        assert_eq!(Matrix4::identity(), flip.world_to_object_at_time(0.5));
+    }
+
+    #[test]
+    fn rays_hit_moving_shapes_where_they_are_at_the_time() {
+        let mut s = Sphere::unit();
+        s.set_motion(Some(Motion::new(
+            translation(0., 0., 0.),
+            translation(0., 4., 0.),
+        )));
+        let r = Ray::new(point(0., 2., -5.), vec(0., 0., 1.));
+
+        assert!(s.ray_intersection(r).is_empty());
+        assert_eq!(2, s.ray_intersection(r.with_time(0.5)).len());
+        assert!(s.ray_intersection(r.with_time(1.)).is_empty());
+    }
+}
@@ -0,0 +1,132 @@
+use crate::*;
+
+// a flat disk of `radius` around the origin in the local xz plane, facing +y.
+// An `inner_radius` above zero cuts a hole out of the middle, making an annulus.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Disk {
+    // object -> world transformation
+    transform: CachedTransform,
+    motion: Option<Motion>,
+    pub material: Material,
+    radius: Float,
+    inner_radius: Float,
+}
+
+impl Disk {
+    pub fn new(transformation: impl Into<Matrix4>, material: Material, radius: Float) -> Disk {
+        Disk::try_new(transformation, material, radius).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_new(
+        transformation: impl Into<Matrix4>,
+        material: Material,
+        radius: Float,
+    ) -> Result<Disk, RtcError> {
+        if radius.is_nan() || radius <= 0. {
+            let message = format!("the radius must be above 0, not {}", radius);
+            return Err(RtcError::invalid_size("Disk", message));
+        }
+        Ok(Disk {
+            transform: CachedTransform::for_object(transformation, "Disk")?,
+            motion: None,
+            material,
+            radius,
+            inner_radius: 0.,
+        })
+    }
+
+    pub fn unit() -> Disk {
+        Disk::new(Matrix4::identity(), Default::default(), 1.)
+    }
+
+    // turns the disk into a ring, eg for a picture frame or a washer
+    pub fn with_inner_radius(self, inner_radius: Float) -> Disk {
+        self.try_with_inner_radius(inner_radius)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_with_inner_radius(mut self, inner_radius: Float) -> Result<Disk, RtcError> {
+        if !(inner_radius >= 0. && inner_radius < self.radius) {
+            let message = format!(
+                "the inner radius must be from 0 up to the radius ({}), not {}",
+                self.radius, inner_radius
+            );
+            return Err(RtcError::invalid_size("Disk", message));
+        }
+        self.inner_radius = inner_radius;
+        Ok(self)
+    }
+
+    pub fn radius(&self) -> Float {
+        self.radius
+    }
+
+    pub fn inner_radius(&self) -> Float {
+        self.inner_radius
+    }
+}
+
+impl Shape for Disk {
+    fn transform(&self) -> &CachedTransform {
+        &self.transform
+    }
+    fn try_set_transform(&mut self, transformation: Matrix4) -> Result<(), RtcError> {
+        self.transform = CachedTransform::for_object(transformation, "Disk")?;
+        Ok(())
+    }
+    fn motion(&self) -> Option<&Motion> {
+        self.motion.as_ref()
+    }
+    fn set_motion(&mut self, motion: Option<Motion>) {
+        self.motion = motion;
+    }
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn local_normal_at(&self, _point: Point3) -> Normal3 {
+        Normal3::new(0., 1., 0.)
+    }
+
+    fn local_uv(&self, point: Point3) -> (Float, Float) {
+        planar_uv(point)
+    }
+
+    fn local_bounds(&self) -> BoundingBox {
+        let r = self.radius;
+        BoundingBox::new(point(-r, 0., -r), point(r, 0., r))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disks_which_could_never_be_hit_give_errors() {
+        let disk = |radius| Disk::try_new(Matrix4::identity(), Default::default(), radius);
+        assert!(disk(-1.).is_err());
+        assert!(disk(0.).is_err());
+        assert!(disk(Float::NAN).is_err());
+
+        let ring = |inner| disk(2.).unwrap().try_with_inner_radius(inner);
+        assert_eq!(
+            "Disk has an invalid size: the inner radius must be from 0 up to the radius (2), not 3",
+            ring(3.).unwrap_err().to_string()
+        );
+        assert!(ring(2.).is_err());
+        assert!(ring(-0.5).is_err());
+        assert_eq!(1.5, ring(1.5).unwrap().inner_radius());
+        assert_eq!(0., ring(0.).unwrap().inner_radius());
+    }
+
+    #[test]
+    fn disks_face_up_and_fit_in_a_flat_box() {
+        let d = Disk::new(translation(0., 2., 0.), Default::default(), 3.);
+        assert_tuple_eq!(Normal3::new(0., 1., 0.), d.normal_at(point(1., 2., 1.)));
+        assert_eq!(
+            BoundingBox::new(point(-3., 0., -3.), point(3., 0., 3.)),
+            d.local_bounds()
+        );
+    }
+}
@@ -2,40 +2,61 @@ use crate::*;
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Plane {
-    world_to_object: Matrix4,
-    pub material: PhongMaterial,
+    // object -> world transformation
+    transform: CachedTransform,
+    motion: Option<Motion>,
+    pub material: Material,
 }
 
 impl Plane {
-    pub fn new(transformation: Matrix4, material: PhongMaterial) -> Plane {
-        let world_to_object = transformation
-            .try_inverse()
-            .expect("Panic! Shape transformation not invertible");
-        Plane {
-            world_to_object,
+    pub fn new(transformation: impl Into<Matrix4>, material: Material) -> Plane {
+        Plane::try_new(transformation, material).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_new(
+        transformation: impl Into<Matrix4>,
+        material: Material,
+    ) -> Result<Plane, RtcError> {
+        Ok(Plane {
+            transform: CachedTransform::for_object(transformation, "Plane")?,
+            motion: None,
             material,
-        }
+        })
     }
 
     pub fn xz() -> Plane {
-        Plane::new(Matrix4::identity(), PhongMaterial::default())
+        Plane::new(Matrix4::identity(), Material::default())
     }
 
-    pub fn t(transformation: Matrix4) -> Plane {
-        Plane::new(transformation, PhongMaterial::default())
+    pub fn t(transformation: impl Into<Matrix4>) -> Plane {
+        Plane::new(transformation, Material::default())
     }
 }
 
 impl Shape for Plane {
-    fn world_to_object(&self) -> Matrix4 {
-        self.world_to_object
+    fn transform(&self) -> &CachedTransform {
+        &self.transform
+    }
+    fn try_set_transform(&mut self, transformation: Matrix4) -> Result<(), RtcError> {
+        self.transform = CachedTransform::for_object(transformation, "Plane")?;
+        Ok(())
+    }
+    fn motion(&self) -> Option<&Motion> {
+        self.motion.as_ref()
     }
-    fn material(&self) -> &PhongMaterial {
+    fn set_motion(&mut self, motion: Option<Motion>) {
+        self.motion = motion;
+    }
+    fn material(&self) -> &Material {
         &self.material
     }
 
-    fn local_normal_at(&self, _point: Tuple) -> Tuple {
-        vec(0., 1., 0.)
+    fn local_normal_at(&self, _point: Point3) -> Normal3 {
+        Normal3::new(0., 1., 0.)
+    }
+
+    fn local_uv(&self, point: Point3) -> (Float, Float) {
+        planar_uv(point)
     }
 }
 
@@ -45,8 +66,17 @@ mod tests {
     #[test]
     fn the_normal_of_a_plane_is_constant_everywhere() {
         let p = Plane::xz();
-        assert_eq!(vec(0., 1., 0.), p.local_normal_at(point(0., 0., 0.)));
-        assert_eq!(vec(0., 1., 0.), p.local_normal_at(point(1., 2., 3.)));
-        assert_eq!(vec(0., 1., 0.), p.local_normal_at(point(10., 10., 10.,)));
+        assert_eq!(
+            Normal3::new(0., 1., 0.),
+            p.local_normal_at(point(0., 0., 0.))
+        );
+        assert_eq!(
+            Normal3::new(0., 1., 0.),
+            p.local_normal_at(point(1., 2., 3.))
+        );
+        assert_eq!(
+            Normal3::new(0., 1., 0.),
+            p.local_normal_at(point(10., 10., 10.,))
+        );
     }
 }
@@ -0,0 +1,128 @@
+use crate::*;
+
+// a flat rectangle (or quad) centred on the origin in the local xz plane, facing +y.
+// It's `width` along the x axis and `depth` along the z axis.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Rectangle {
+    // object -> world transformation
+    transform: CachedTransform,
+    motion: Option<Motion>,
+    pub material: Material,
+    width: Float,
+    depth: Float,
+}
+
+impl Rectangle {
+    pub fn new(
+        transformation: impl Into<Matrix4>,
+        material: Material,
+        width: Float,
+        depth: Float,
+    ) -> Rectangle {
+        Rectangle::try_new(transformation, material, width, depth)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_new(
+        transformation: impl Into<Matrix4>,
+        material: Material,
+        width: Float,
+        depth: Float,
+    ) -> Result<Rectangle, RtcError> {
+        if !(width > 0. && depth > 0.) {
+            let message = format!(
+                "the width and depth must be above 0, not {} and {}",
+                width, depth
+            );
+            return Err(RtcError::invalid_size("Rectangle", message));
+        }
+        Ok(Rectangle {
+            transform: CachedTransform::for_object(transformation, "Rectangle")?,
+            motion: None,
+            material,
+            width,
+            depth,
+        })
+    }
+
+    // a 2x2 square, the same size as the side of a unit cube
+    pub fn unit() -> Rectangle {
+        Rectangle::new(Matrix4::identity(), Default::default(), 2., 2.)
+    }
+
+    pub fn width(&self) -> Float {
+        self.width
+    }
+
+    pub fn depth(&self) -> Float {
+        self.depth
+    }
+}
+
+impl Shape for Rectangle {
+    fn transform(&self) -> &CachedTransform {
+        &self.transform
+    }
+    fn try_set_transform(&mut self, transformation: Matrix4) -> Result<(), RtcError> {
+        self.transform = CachedTransform::for_object(transformation, "Rectangle")?;
+        Ok(())
+    }
+    fn motion(&self) -> Option<&Motion> {
+        self.motion.as_ref()
+    }
+    fn set_motion(&mut self, motion: Option<Motion>) {
+        self.motion = motion;
+    }
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn local_normal_at(&self, _point: Point3) -> Normal3 {
+        Normal3::new(0., 1., 0.)
+    }
+
+    fn local_uv(&self, point: Point3) -> (Float, Float) {
+        planar_uv(point)
+    }
+
+    fn local_bounds(&self) -> BoundingBox {
+        let (x, z) = (self.width / 2., self.depth / 2.);
+        BoundingBox::new(point(-x, 0., -z), point(x, 0., z))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rectangles_which_could_never_be_hit_give_errors() {
+        let rectangle = |w, d| Rectangle::try_new(Matrix4::identity(), Default::default(), w, d);
+        assert_eq!(
+            "Rectangle has an invalid size: the width and depth must be above 0, not -1 and 2",
+            rectangle(-1., 2.).unwrap_err().to_string()
+        );
+        assert!(rectangle(1., 0.).is_err());
+        assert!(rectangle(1., Float::NAN).is_err());
+        assert!(rectangle(1., 2.).is_ok());
+    }
+
+    #[test]
+    fn rectangles_face_up_and_fit_in_a_flat_box() {
+        let r = Rectangle::new(
+            rotation_x(crate::consts::PI / 2.),
+            Default::default(),
+            4.,
+            2.,
+        );
+        assert_tuple_eq!(
+            Normal3::new(0., 0., 1.),
+            r.normal_at(point(1., 0.5, 0.)),
+            epsilon = 0.00001
+        );
+        assert_eq!(
+            BoundingBox::new(point(-2., 0., -1.), point(2., 0., 1.)),
+            r.local_bounds()
+        );
+    }
+}
@@ -2,26 +2,33 @@ use crate::*;
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Sphere {
-    world_to_object: Matrix4,
-    pub material: PhongMaterial,
+    // object -> world transformation
+    transform: CachedTransform,
+    motion: Option<Motion>,
+    pub material: Material,
 }
 
 impl Sphere {
-    pub fn new(transformation: Matrix4, material: PhongMaterial) -> Sphere {
-        let world_to_object = transformation
-            .try_inverse()
-            .expect("Panic! Shape transformation not invertible");
-        Sphere {
-            world_to_object,
+    pub fn new(transformation: impl Into<Matrix4>, material: Material) -> Sphere {
+        Sphere::try_new(transformation, material).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_new(
+        transformation: impl Into<Matrix4>,
+        material: Material,
+    ) -> Result<Sphere, RtcError> {
+        Ok(Sphere {
+            transform: CachedTransform::for_object(transformation, "Sphere")?,
+            motion: None,
             material,
-        }
+        })
     }
 
     pub fn unit() -> Sphere {
         Sphere::new(Matrix4::identity(), Default::default())
     }
 
-    pub fn pos_r(position: Tuple, r: f32) -> Sphere {
+    pub fn pos_r(position: Point3, r: Float) -> Sphere {
         Sphere::new(
             translation(position.x, position.y, position.z)
                 * scaling(r, r, r)
@@ -30,7 +37,7 @@ impl Sphere {
         )
     }
 
-    pub fn pos_r_m(position: Tuple, r: f32, material: PhongMaterial) -> Sphere {
+    pub fn pos_r_m(position: Point3, r: Float, material: Material) -> Sphere {
         Sphere::new(
             translation(position.x, position.y, position.z)
                 * scaling(r, r, r)
@@ -41,22 +48,40 @@ impl Sphere {
 }
 
 impl Shape for Sphere {
-    fn world_to_object(&self) -> Matrix4 {
-        self.world_to_object
+    fn transform(&self) -> &CachedTransform {
+        &self.transform
+    }
+    fn try_set_transform(&mut self, transformation: Matrix4) -> Result<(), RtcError> {
+        self.transform = CachedTransform::for_object(transformation, "Sphere")?;
+        Ok(())
+    }
+    fn motion(&self) -> Option<&Motion> {
+        self.motion.as_ref()
+    }
+    fn set_motion(&mut self, motion: Option<Motion>) {
+        self.motion = motion;
     }
-    fn material(&self) -> &PhongMaterial {
+    fn material(&self) -> &Material {
         &self.material
     }
 
-    fn local_normal_at(&self, p: Tuple) -> Tuple {
-        p - point(0., 0., 0.)
+    fn local_normal_at(&self, p: Point3) -> Normal3 {
+        (p - Point3::origin()).into()
+    }
+
+    fn local_uv(&self, p: Point3) -> (Float, Float) {
+        spherical_uv(p)
+    }
+
+    fn local_bounds(&self) -> BoundingBox {
+        BoundingBox::new(point(-1., -1., -1.), point(1., 1., 1.))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::f32::consts::PI;
+    use crate::consts::PI;
 
     #[test]
     fn normal_at_points_on_unit_sphere() {
@@ -64,25 +89,25 @@ mod tests {
 
         // normal on x axis points in x direction
         let n1 = s.normal_at(point(1., 0., 0.));
-        assert_tuple_eq!(vec(1., 0., 0.), n1);
+        assert_tuple_eq!(Normal3::new(1., 0., 0.), n1);
 
-        let r33 = 3_f32.sqrt() / 3_f32;
+        let r33 = Float::sqrt(3.) / 3.;
         let n2 = s.normal_at(point(r33, r33, r33));
-        assert_tuple_eq!(vec(r33, r33, r33), n2);
+        assert_tuple_eq!(Normal3::new(r33, r33, r33), n2);
     }
 
     #[test]
     fn normal_at_points_on_translated_sphere() {
         let s1 = Sphere::pos_r(point(0., 1., 0.), 1.);
         let n1 = s1.normal_at(point(0., 1.707111, -0.70711));
-        assert_tuple_eq!(n1, vec(0., 0.70711, -0.70711), epsilon = 0.0001);
+        assert_tuple_eq!(n1, Normal3::new(0., 0.70711, -0.70711), epsilon = 0.0001);
 
         let s2 = Sphere::new(
             scaling(1., 0.5, 1.) * rotation_z(PI / 5.),
             Default::default(),
         );
-        let s22 = 2_f32.sqrt() / 2.;
+        let s22 = Float::sqrt(2.) / 2.;
         let n2 = s2.normal_at(point(0., s22, -s22));
-        assert_tuple_eq!(n2, vec(0., 0.97014, -0.24254), epsilon = 0.00001);
+        assert_tuple_eq!(n2, Normal3::new(0., 0.97014, -0.24254), epsilon = 0.00001);
     }
 }
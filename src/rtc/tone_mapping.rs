@@ -0,0 +1,194 @@
+use crate::*;
+
+// compresses the unbounded range of rendered colors into the [0, 1] range that can be displayed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToneMapper {
+    // anything brighter than 1 is cut off
+    Clamp,
+    // x / (1 + x): never quite reaches white, but keeps detail in bright areas
+    Reinhard,
+    // an approximation of the ACES filmic curve, with a gentle shoulder and toe
+    AcesFilmic,
+}
+
+impl ToneMapper {
+    pub fn map(&self, c: &Color) -> Color {
+        match self {
+            ToneMapper::Clamp => c.clamp(),
+            ToneMapper::Reinhard => {
+                let reinhard = |x: Float| {
+                    let x = x.max(0.);
+                    x / (1. + x)
+                };
+                Color::new(reinhard(c.r), reinhard(c.g), reinhard(c.b))
+            }
+            ToneMapper::AcesFilmic => {
+                // curve fit by Krzysztof Narkowicz, see
+                // https://knarkowicz.wordpress.com/2016/01/06/aces-filmic-tone-mapping-curve/
+                let aces = |x: Float| {
+                    let x = x.max(0.);
+                    (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)
+                };
+                Color::new(aces(c.r), aces(c.g), aces(c.b)).clamp()
+            }
+        }
+    }
+}
+
+// everything that happens to a linear rendered color before it's written to
+// an 8 bit image or shown on screen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DisplayTransform {
+    // in stops, so +1 doubles the brightness of the image
+    pub exposure: Float,
+    pub tone_mapper: ToneMapper,
+    // whether to encode colors with the sRGB transfer function, which is
+    // what most image viewers expect. Otherwise values are written linearly.
+    pub srgb: bool,
+}
+
+impl DisplayTransform {
+    // the colors are just clamped and written out as-is
+    pub fn linear() -> DisplayTransform {
+        DisplayTransform {
+            exposure: 0.,
+            tone_mapper: ToneMapper::Clamp,
+            srgb: false,
+        }
+    }
+
+    pub fn srgb(tone_mapper: ToneMapper, exposure: Float) -> DisplayTransform {
+        DisplayTransform {
+            exposure,
+            tone_mapper,
+            srgb: true,
+        }
+    }
+
+    // a color in the [0, 1] range, ready to be quantized
+    pub fn apply(&self, c: &Color) -> Color {
+        let exposed = c * Float::powf(2., self.exposure);
+        let mapped = self.tone_mapper.map(&exposed);
+        if self.srgb {
+            Color::new(
+                linear_to_srgb(mapped.r),
+                linear_to_srgb(mapped.g),
+                linear_to_srgb(mapped.b),
+            )
+        } else {
+            mapped
+        }
+    }
+
+    pub fn to_u8(&self, c: &Color) -> (u8, u8, u8) {
+        self.apply(c).clamp().to_u8()
+    }
+
+    pub fn to_u16(&self, c: &Color) -> (u16, u16, u16) {
+        let c = self.apply(c).clamp();
+        (unit_to_u16(c.r), unit_to_u16(c.g), unit_to_u16(c.b))
+    }
+}
+
+impl Default for DisplayTransform {
+    fn default() -> DisplayTransform {
+        DisplayTransform::linear()
+    }
+}
+
+fn unit_to_u16(x: Float) -> u16 {
+    (x * 65535.).round() as u16
+}
+
+// the sRGB transfer function ("gamma"), see https://en.wikipedia.org/wiki/SRGB#Transfer_function_(%22gamma%22)
+pub fn linear_to_srgb(x: Float) -> Float {
+    if x <= 0.003_130_8 {
+        x * 12.92
+    } else {
+        1.055 * x.powf(1. / 2.4) - 0.055
+    }
+}
+
+// the inverse of `linear_to_srgb`, for reading sRGB encoded images
+pub fn srgb_to_linear(x: Float) -> Float {
+    if x <= 0.040_45 {
+        x / 12.92
+    } else {
+        ((x + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn srgb_transfer_function() {
+        assert_eq!(0., linear_to_srgb(0.));
+        assert!(approx_eq!(Float, 1., linear_to_srgb(1.), epsilon = 0.00001));
+        assert!(approx_eq!(
+            Float,
+            0.73536,
+            linear_to_srgb(0.5),
+            epsilon = 0.00001
+        ));
+        // the linear segment near black joins up with the curve
+        assert!(approx_eq!(
+            Float,
+            0.04045,
+            linear_to_srgb(0.003_130_8),
+            epsilon = 0.00001
+        ));
+        for x in &[0., 0.002, 0.04, 0.5, 1.] {
+            assert!(approx_eq!(
+                Float,
+                *x,
+                srgb_to_linear(linear_to_srgb(*x)),
+                epsilon = 0.00001
+            ));
+        }
+    }
+
+    #[test]
+    fn reinhard_compresses_highlights() {
+        let c = ToneMapper::Reinhard.map(&Color::new(0., 1., 3.));
+        assert_eq!(Color::new(0., 0.5, 0.75), c);
+    }
+
+    #[test]
+    fn aces_keeps_black_and_saturates_to_white() {
+        assert_eq!(black(), ToneMapper::AcesFilmic.map(&black()));
+        assert_eq!(white(), ToneMapper::AcesFilmic.map(&grey(100.)));
+        let mid = ToneMapper::AcesFilmic.map(&grey(0.18));
+        let bright = ToneMapper::AcesFilmic.map(&grey(0.5));
+        assert!(mid.r < bright.r && bright.r < 1.);
+    }
+
+    #[test]
+    fn linear_transform_is_the_same_as_clamping() {
+        let t = DisplayTransform::linear();
+        for c in &[
+            Color::new(1.5, 0., 0.),
+            Color::new(0., 0.5, 0.),
+            Color::new(-0.5, 0., 1.),
+        ] {
+            assert_eq!(c.clamp().to_u8(), t.to_u8(c));
+        }
+    }
+
+    #[test]
+    fn exposure_is_measured_in_stops() {
+        let mut t = DisplayTransform::linear();
+        t.exposure = 1.;
+        assert_eq!(grey(0.5), t.apply(&grey(0.25)));
+        t.exposure = -2.;
+        assert_eq!(grey(0.25), t.apply(&grey(1.)));
+    }
+
+    #[test]
+    fn srgb_output_brightens_midtones() {
+        let t = DisplayTransform::srgb(ToneMapper::Clamp, 0.);
+        assert_eq!((188, 188, 188), t.to_u8(&grey(0.5)));
+        assert_eq!((255, 255, 255), t.to_u8(&grey(2.)));
+    }
+}
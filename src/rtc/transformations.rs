@@ -1,7 +1,7 @@
 use crate::*;
 extern crate approx;
 
-pub fn translation(x: f32, y: f32, z: f32) -> Matrix4 {
+pub fn translation(x: Float, y: Float, z: Float) -> Matrix4 {
     Matrix4::new(
         1., 0., 0., x, //
         0., 1., 0., y, //
@@ -10,7 +10,7 @@ pub fn translation(x: f32, y: f32, z: f32) -> Matrix4 {
     )
 }
 
-pub fn scaling(x: f32, y: f32, z: f32) -> Matrix4 {
+pub fn scaling(x: Float, y: Float, z: Float) -> Matrix4 {
     Matrix4::new(
         x, 0., 0., 0., //
         0., y, 0., 0., //
@@ -20,7 +20,7 @@ pub fn scaling(x: f32, y: f32, z: f32) -> Matrix4 {
 }
 
 #[rustfmt::skip]
-pub fn rotation_x(rad: f32) -> Matrix4 {
+pub fn rotation_x(rad: Float) -> Matrix4 {
     Matrix4::new(
         1., 0.,        0.,         0.,
         0., rad.cos(), -rad.sin(), 0.,
@@ -30,7 +30,7 @@ pub fn rotation_x(rad: f32) -> Matrix4 {
 }
 
 #[rustfmt::skip]
-pub fn rotation_y(rad: f32) -> Matrix4 {
+pub fn rotation_y(rad: Float) -> Matrix4 {
     Matrix4::new(
         rad.cos(),  0., rad.sin(), 0.,
         0.,         1., 0.,        0.,
@@ -40,7 +40,7 @@ pub fn rotation_y(rad: f32) -> Matrix4 {
 }
 
 #[rustfmt::skip]
-pub fn rotation_z(rad: f32) -> Matrix4 {
+pub fn rotation_z(rad: Float) -> Matrix4 {
     Matrix4::new(
         rad.cos(), -rad.sin(), 0., 0.,
         rad.sin(),  rad.cos(), 0., 0.,
@@ -50,7 +50,7 @@ pub fn rotation_z(rad: f32) -> Matrix4 {
 }
 
 #[rustfmt::skip]
-pub fn shearing(x_from_y: f32, x_from_z: f32, y_from_x: f32, y_from_z: f32, z_from_x: f32, z_from_y: f32) -> Matrix4 {
+pub fn shearing(x_from_y: Float, x_from_z: Float, y_from_x: Float, y_from_z: Float, z_from_x: Float, z_from_y: Float) -> Matrix4 {
     Matrix4::new(
         1.,       x_from_y, x_from_z, 0.,
         y_from_x, 1.,       y_from_z, 0.,
@@ -61,11 +61,7 @@ pub fn shearing(x_from_y: f32, x_from_z: f32, y_from_x: f32, y_from_z: f32, z_fr
 
 // a transform from world space into camera space, where the camera is
 // at `from`, facing `to`, with `up` defining the camera rotation
-pub fn view_transform(from: Tuple, to: Tuple, up: Tuple) -> Matrix4 {
-    assert!(from.is_point());
-    assert!(to.is_point());
-    assert!(up.is_vec());
-
+pub fn view_transform(from: Point3, to: Point3, up: Vector3) -> Matrix4 {
     let forward = (to - from).normalize();
     let left = forward.cross(&up.normalize());
     // TODO: figure out the difference between `up` and `true_up`
@@ -80,13 +76,298 @@ pub fn view_transform(from: Tuple, to: Tuple, up: Tuple) -> Matrix4 {
     orientation * translation(-from.x, -from.y, -from.z)
 }
 
+// a rotation of `rad` radians around any axis, see `Quaternion::from_axis_angle`
+pub fn rotation_axis(axis: Vector3, rad: Float) -> Matrix4 {
+    Quaternion::from_axis_angle(axis, rad).to_matrix()
+}
+
+// a transformation which places an object at `from`, turned to face `to` with its
+// y axis as close to `up` as possible. Objects face down their -z axis, like the
+// camera does, so this undoes `view_transform` (apart from the scaling it does
+// when `up` isn't perpendicular to the view direction).
+pub fn look_at(from: Point3, to: Point3, up: Vector3) -> Matrix4 {
+    let forward = (to - from).normalize();
+    let left = forward.cross(&up).normalize();
+    let true_up = left.cross(&forward);
+    let orientation = Matrix4::new(
+        left.x, true_up.x, -forward.x, 0., //
+        left.y, true_up.y, -forward.y, 0., //
+        left.z, true_up.z, -forward.z, 0., //
+        0., 0., 0., 1.,
+    );
+
+    translation(from.x, from.y, from.z) * orientation
+}
+
+// a transformation split into a scaling, then a rotation, then a translation
+// ("TRS", since the matrix is `translation * rotation * scaling`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decomposition {
+    pub translation: Vector3,
+    pub rotation: Quaternion,
+    pub scale: Vector3,
+}
+
+impl Decomposition {
+    pub fn to_matrix(&self) -> Matrix4 {
+        let (t, s) = (self.translation, self.scale);
+        translation(t.x, t.y, t.z) * self.rotation.to_matrix() * scaling(s.x, s.y, s.z)
+    }
+
+    // blends the translation and scale linearly, and the rotation with `Quaternion::slerp`
+    pub fn interpolate(&self, other: &Decomposition, t: Float) -> Decomposition {
+        Decomposition {
+            translation: self.translation * (1. - t) + other.translation * t,
+            rotation: self.rotation.slerp(&other.rotation, t),
+            scale: self.scale * (1. - t) + other.scale * t,
+        }
+    }
+
+    // the inverse of `to_matrix`, put together from the inverse of each part rather
+    // than with a general matrix inverse. Gives None if any of the scales is 0.
+    pub fn inverse_matrix(&self) -> Option<Matrix4> {
+        let (t, s) = (self.translation, self.scale);
+        if s.x.abs() <= EPSILON || s.y.abs() <= EPSILON || s.z.abs() <= EPSILON {
+            return None;
+        }
+        Some(
+            scaling(1. / s.x, 1. / s.y, 1. / s.z)
+                * self.rotation.conjugate().to_matrix()
+                * translation(-t.x, -t.y, -t.z),
+        )
+    }
+}
+
+// splits a transformation up into its parts, or gives None if it can't be. That
+// happens for matrices which are singular, or which shear (or project) things.
+// A mirror image comes out as a negative x scale.
+pub fn decompose(m: &Matrix4) -> Option<Decomposition> {
+    if m[(3, 0)] != 0. || m[(3, 1)] != 0. || m[(3, 2)] != 0. || m[(3, 3)] != 1. {
+        return None;
+    }
+
+    // each column of the 3x3 part is a rotated and scaled axis
+    let column = |c: usize| vec(m[(0, c)], m[(1, c)], m[(2, c)]);
+    let (x, y, z) = (column(0), column(1), column(2));
+    let mut scale = vec(x.magnitude(), y.magnitude(), z.magnitude());
+    if scale.x <= EPSILON || scale.y <= EPSILON || scale.z <= EPSILON {
+        return None;
+    }
+    if x.cross(&y).dot(z) < 0. {
+        scale.x = -scale.x;
+    }
+
+    let (x, y, z) = (x / scale.x, y / scale.y, z / scale.z);
+    let tolerance = 0.0001;
+    if x.dot(y).abs() > tolerance || x.dot(z).abs() > tolerance || y.dot(z).abs() > tolerance {
+        return None;
+    }
+
+    let rotation = Matrix4::new(
+        x.x, y.x, z.x, 0., //
+        x.y, y.y, z.y, 0., //
+        x.z, y.z, z.z, 0., //
+        0., 0., 0., 1.,
+    );
+    Some(Decomposition {
+        translation: column(3),
+        rotation: Quaternion::from_matrix(&rotation),
+        scale,
+    })
+}
+
+// a transformation part of the way (`t` from 0 to 1) between two others. Where both can
+// be decomposed, rotations turn smoothly rather than squashing the object on the way.
+pub fn interpolate_transforms(a: &Matrix4, b: &Matrix4, t: Float) -> Matrix4 {
+    match (decompose(a), decompose(b)) {
+        (Some(a), Some(b)) => a.interpolate(&b, t).to_matrix(),
+        _ => a * (1. - t) + b * t,
+    }
+}
+
+// the transformations between two others, as given by `interpolate_transforms`. Both
+// ends are decomposed once up front, so that the inverse part of the way between them
+// can be found quickly for every ray (eg for motion blur).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransformBlend {
+    start: CachedTransform,
+    end: CachedTransform,
+    // both ends split into their parts, when they can be
+    parts: Option<(Decomposition, Decomposition)>,
+}
+
+impl TransformBlend {
+    pub fn new(start: CachedTransform, end: CachedTransform) -> TransformBlend {
+        let parts = match (decompose(&start.matrix()), decompose(&end.matrix())) {
+            (Some(a), Some(b)) => Some((a, b)),
+            _ => None,
+        };
+        TransformBlend { start, end, parts }
+    }
+
+    pub fn start(&self) -> CachedTransform {
+        self.start
+    }
+
+    pub fn end(&self) -> CachedTransform {
+        self.end
+    }
+
+    // the inverse of the transformation `t` (from 0 to 1) of the way from start to end.
+    // Both ends can be inverted, but a blend between them might not be (eg when a
+    // mirrored scale flips over), so then we use whichever end is nearer.
+    pub fn inverse_at(&self, t: Float) -> Matrix4 {
+        let t = t.clamp(0., 1.);
+        let inverse = match &self.parts {
+            Some((a, b)) => a.interpolate(b, t).inverse_matrix(),
+            None => (self.start.matrix() * (1. - t) + self.end.matrix() * t).try_inverse(),
+        };
+        inverse.unwrap_or_else(|| {
+            if t < 0.5 {
+                self.start.inverse()
+            } else {
+                self.end.inverse()
+            }
+        })
+    }
+}
+
+// builds up a transformation one step at a time, in the order the steps happen:
+// `Transform::identity().rotate_x(a).scale(2., 2., 2.).translate(0., 1., 0.)` is
+// the same as `translation(0., 1., 0.) * scaling(2., 2., 2.) * rotation_x(a)`.
+// Anything which takes a transformation matrix can take one of these instead.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    matrix: Matrix4,
+}
+
+impl Transform {
+    pub fn identity() -> Transform {
+        Transform {
+            matrix: Matrix4::identity(),
+        }
+    }
+
+    pub fn matrix(&self) -> Matrix4 {
+        self.matrix
+    }
+
+    // applies another transformation after the ones so far
+    pub fn then(self, next: impl Into<Matrix4>) -> Transform {
+        Transform {
+            matrix: next.into() * self.matrix,
+        }
+    }
+
+    pub fn translate(self, x: Float, y: Float, z: Float) -> Transform {
+        self.then(translation(x, y, z))
+    }
+
+    pub fn scale(self, x: Float, y: Float, z: Float) -> Transform {
+        self.then(scaling(x, y, z))
+    }
+
+    pub fn scale_uniform(self, s: Float) -> Transform {
+        self.scale(s, s, s)
+    }
+
+    pub fn rotate_x(self, rad: Float) -> Transform {
+        self.then(rotation_x(rad))
+    }
+
+    pub fn rotate_y(self, rad: Float) -> Transform {
+        self.then(rotation_y(rad))
+    }
+
+    pub fn rotate_z(self, rad: Float) -> Transform {
+        self.then(rotation_z(rad))
+    }
+
+    pub fn rotate(self, axis: Vector3, rad: Float) -> Transform {
+        self.then(rotation_axis(axis, rad))
+    }
+
+    pub fn shear(
+        self,
+        x_from_y: Float,
+        x_from_z: Float,
+        y_from_x: Float,
+        y_from_z: Float,
+        z_from_x: Float,
+        z_from_y: Float,
+    ) -> Transform {
+        self.then(shearing(
+            x_from_y, x_from_z, y_from_x, y_from_z, z_from_x, z_from_y,
+        ))
+    }
+}
+
+impl From<Transform> for Matrix4 {
+    fn from(t: Transform) -> Matrix4 {
+        t.matrix
+    }
+}
+
+// a transformation along with its inverse and inverse transpose, which are worked out
+// once up front rather than every time a ray or a normal needs them
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CachedTransform {
+    matrix: Matrix4,
+    inverse: Matrix4,
+    inverse_transpose: Matrix4,
+}
+
+impl CachedTransform {
+    // gives None if the matrix can't be inverted
+    pub fn new(matrix: impl Into<Matrix4>) -> Option<CachedTransform> {
+        let matrix = matrix.into();
+        let inverse = matrix.try_inverse()?;
+        Some(CachedTransform {
+            matrix,
+            inverse,
+            inverse_transpose: inverse.transpose(),
+        })
+    }
+
+    // like `new`, with an error saying which object the transformation belonged to.
+    // Each `try_new` uses this (see `RtcError` for how those fit with `new`).
+    pub fn for_object(
+        matrix: impl Into<Matrix4>,
+        object: &str,
+    ) -> Result<CachedTransform, RtcError> {
+        let matrix = matrix.into();
+        CachedTransform::new(matrix).ok_or_else(|| RtcError::singular_transform(object, matrix))
+    }
+
+    pub fn identity() -> CachedTransform {
+        CachedTransform {
+            matrix: Matrix4::identity(),
+            inverse: Matrix4::identity(),
+            inverse_transpose: Matrix4::identity(),
+        }
+    }
+
+    pub fn matrix(&self) -> Matrix4 {
+        self.matrix
+    }
+
+    pub fn inverse(&self) -> Matrix4 {
+        self.inverse
+    }
+
+    // for transforming normals, see `Normal3::transform_by_inverse_transpose`
+    pub fn inverse_transpose(&self) -> Matrix4 {
+        self.inverse_transpose
+    }
+}
+
 #[cfg(test)]
 mod tests {
     extern crate float_cmp;
     use self::approx::assert_relative_eq;
     use self::float_cmp::approx_eq;
     use super::*;
-    use std::f32::consts::PI;
+    use crate::consts::PI;
 
     #[test]
     fn translating_points_should_move_them() {
@@ -129,22 +410,22 @@ mod tests {
 
         let half_quarter_x_rotation = rotation_x(PI / 4.);
         let quarter_x_rotation = rotation_x(PI / 2.);
-        let half_root_2 = 2_f32.sqrt() / 2.;
+        let half_root_2 = Float::sqrt(2.) / 2.;
 
         assert!(approx_eq!(
-            Tuple,
+            Point3,
             point(0., half_root_2, half_root_2),
             half_quarter_x_rotation * p1
         ));
 
         assert!(approx_eq!(
-            Tuple,
+            Point3,
             point(0., half_root_2, -half_root_2),
             (half_quarter_x_rotation.try_inverse().unwrap()) * p1
         ));
 
         assert!(approx_eq!(
-            Tuple,
+            Point3,
             point(0., 0., 1.),
             quarter_x_rotation * p1
         ));
@@ -156,22 +437,22 @@ mod tests {
 
         let half_quarter_y_rotation = rotation_y(PI / 4.);
         let quarter_y_rotation = rotation_y(PI / 2.);
-        let half_root_2 = 2_f32.sqrt() / 2.;
+        let half_root_2 = Float::sqrt(2.) / 2.;
 
         assert!(approx_eq!(
-            Tuple,
+            Point3,
             point(half_root_2, 0., half_root_2),
             half_quarter_y_rotation * p1
         ));
 
         assert!(approx_eq!(
-            Tuple,
+            Point3,
             point(-half_root_2, 0., half_root_2),
             half_quarter_y_rotation.try_inverse().unwrap() * p1
         ));
 
         assert!(approx_eq!(
-            Tuple,
+            Point3,
             point(1., 0., 0.),
             quarter_y_rotation * p1
         ));
@@ -183,22 +464,22 @@ mod tests {
 
         let half_quarter_z_rotation = rotation_z(PI / 4.);
         let quarter_z_rotation = rotation_z(PI / 2.);
-        let half_root_2 = 2_f32.sqrt() / 2.;
+        let half_root_2 = Float::sqrt(2.) / 2.;
 
         assert!(approx_eq!(
-            Tuple,
+            Point3,
             point(-half_root_2, half_root_2, 0.),
             half_quarter_z_rotation * p1
         ));
 
         assert!(approx_eq!(
-            Tuple,
+            Point3,
             point(half_root_2, half_root_2, 0.),
             half_quarter_z_rotation.try_inverse().unwrap() * p1
         ));
 
         assert!(approx_eq!(
-            Tuple,
+            Point3,
             point(-1., 0., 0.),
             quarter_z_rotation * p1
         ));
@@ -235,32 +516,20 @@ mod tests {
     fn default_view_transform_is_identity_matrix() {
         // the default view transform actually looks along the z axis in the negative direction-
         // adding a camera requires things to be flipped
-        let vt = view_transform(
-            point(0., 0., 0.),
-            point(0., 0., -1.),
-            vec(0., 1., 0.),
-        );
+        let vt = view_transform(point(0., 0., 0.), point(0., 0., -1.), vec(0., 1., 0.));
         assert_eq!(Matrix4::identity(), vt);
     }
 
     #[test]
     fn view_transform_for_positive_z_axis_direction() {
-        let vt = view_transform(
-            point(0., 0., 0.),
-            point(0., 0., 1.),
-            vec(0., 1., 0.),
-        );
+        let vt = view_transform(point(0., 0., 0.), point(0., 0., 1.), vec(0., 1., 0.));
         // now we flip the z and x axes
         assert_eq!(scaling(-1., 1., -1.), vt);
     }
 
     #[test]
     fn the_view_transform_moves_the_world() {
-        let vt = view_transform(
-            point(0., 0., 8.),
-            point(0., 0., 0.),
-            vec(0., 1., 0.),
-        );
+        let vt = view_transform(point(0., 0., 8.), point(0., 0., 0.), vec(0., 1., 0.));
         // if the camera is at z=8, then we shift the whole world -8 on z axis to put the camera at the origin
         assert_eq!(translation(0., 0., -8.), vt);
     }
@@ -268,11 +537,7 @@ mod tests {
     #[test]
     fn an_arbitrary_view_transform() {
         // example from book
-        let vt = view_transform(
-            point(1., 3., 2.),
-            point(4., -2., 8.),
-            vec(1., 1., 0.),
-        );
+        let vt = view_transform(point(1., 3., 2.), point(4., -2., 8.), vec(1., 1., 0.));
         let expected = Matrix4::new(
             -0.50709, 0.50709, 0.67612, -2.36643, //
             0.76772, 0.60609, 0.12122, -2.82843, //
@@ -281,4 +546,199 @@ mod tests {
         );
         assert_relative_eq!(expected, vt, epsilon = 0.00001);
     }
+
+    #[test]
+    fn rotating_around_an_arbitrary_axis() {
+        assert_relative_eq!(
+            rotation_y(0.4),
+            rotation_axis(vec(0., 3., 0.), 0.4),
+            epsilon = 0.00001
+        );
+
+        // a half turn around the x = y diagonal swaps x and y over
+        let m = rotation_axis(vec(1., 1., 0.), PI);
+        assert_tuple_eq!(point(2., 1., -3.), m * point(1., 2., 3.), epsilon = 0.00001);
+    }
+
+    #[test]
+    fn look_at_places_objects_facing_a_point() {
+        let m = look_at(point(1., 2., 3.), point(1., 2., -5.), vec(0., 1., 0.));
+        assert_relative_eq!(translation(1., 2., 3.), m, epsilon = 0.00001);
+
+        let from = point(0., 0., 8.);
+        let to = point(0., 0., 0.);
+        assert_relative_eq!(
+            view_transform(from, to, vec(0., 1., 0.))
+                .try_inverse()
+                .unwrap(),
+            look_at(from, to, vec(0., 1., 0.)),
+            epsilon = 0.00001
+        );
+
+        let from = point(1., 3., 2.);
+        let to = point(4., -2., 8.);
+        let m = look_at(from, to, vec(1., 1., 0.));
+        // only rotated and moved, not scaled
+        let d = decompose(&m).unwrap();
+        assert_tuple_eq!(vec(1., 1., 1.), d.scale, epsilon = 0.00001);
+        assert_tuple_eq!(vec(1., 3., 2.), d.translation, epsilon = 0.00001);
+        // the object's -z axis points at `to`
+        assert_tuple_eq!(
+            (to - from).normalize(),
+            (m * vec(0., 0., -1.)).normalize(),
+            epsilon = 0.00001
+        );
+    }
+
+    #[test]
+    fn decomposing_transformations() {
+        let m =
+            translation(1., -2., 3.) * rotation_y(0.8) * rotation_x(-0.3) * scaling(2., 0.5, 3.);
+        let d = decompose(&m).unwrap();
+        assert_tuple_eq!(vec(1., -2., 3.), d.translation, epsilon = 0.00001);
+        assert_tuple_eq!(vec(2., 0.5, 3.), d.scale, epsilon = 0.00001);
+        assert_relative_eq!(
+            rotation_y(0.8) * rotation_x(-0.3),
+            d.rotation.to_matrix(),
+            epsilon = 0.00001
+        );
+        assert_relative_eq!(m, d.to_matrix(), epsilon = 0.00001);
+
+        // mirror images have a negative scale
+        let mirrored = rotation_z(0.5) * scaling(1., -1., 1.);
+        assert_relative_eq!(
+            mirrored,
+            decompose(&mirrored).unwrap().to_matrix(),
+            epsilon = 0.00001
+        );
+
+        assert!(decompose(&shearing(1., 0., 0., 0., 0., 0.)).is_none());
+        assert!(decompose(&scaling(1., 0., 1.)).is_none());
+    }
+
+    #[test]
+    fn interpolating_transformations_turns_smoothly() {
+        let a = translation(0., 0., 0.);
+        let b = translation(2., 0., 0.) * rotation_y(PI / 2.) * scaling(3., 3., 3.);
+        let half = interpolate_transforms(&a, &b, 0.5);
+        assert_relative_eq!(
+            translation(1., 0., 0.) * rotation_y(PI / 4.) * scaling(2., 2., 2.),
+            half,
+            epsilon = 0.00001
+        );
+
+        // blending the matrices directly would shrink the object part way through
+        // a half turn, but interpolating the rotation keeps it the same size
+        let turned = interpolate_transforms(&a, &rotation_z(PI * 0.9), 0.5);
+        assert!(approx_eq!(
+            Float,
+            1.,
+            (turned * vec(1., 0., 0.)).magnitude(),
+            epsilon = 0.00001
+        ));
+
+        // transformations which can't be decomposed are blended directly
+        let sheared = shearing(1., 0., 0., 0., 0., 0.);
+        assert_relative_eq!(
+            a * 0.5 + sheared * 0.5,
+            interpolate_transforms(&a, &sheared, 0.5),
+            epsilon = 0.00001
+        );
+    }
+
+    #[test]
+    fn blended_inverses_match_inverting_the_blend() {
+        let a = translation(1., 2., 3.) * rotation_x(0.4) * scaling(1.5, 2., 1.);
+        let b = translation(-2., 0., 1.) * rotation_y(2.5) * scaling(3., 1., 0.5);
+        let blend = TransformBlend::new(
+            CachedTransform::new(a).unwrap(),
+            CachedTransform::new(b).unwrap(),
+        );
+        for &t in &[0., 0.25, 0.7, 1.] {
+            assert_relative_eq!(
+                interpolate_transforms(&a, &b, t).try_inverse().unwrap(),
+                blend.inverse_at(t),
+                epsilon = 0.0001
+            );
+        }
+
+        // ends which can't be decomposed are blended directly
+        let sheared = shearing(1., 0., 0., 0., 0., 0.);
+        let blend = TransformBlend::new(
+            CachedTransform::new(a).unwrap(),
+            CachedTransform::new(sheared).unwrap(),
+        );
+        assert_relative_eq!(
+            interpolate_transforms(&a, &sheared, 0.5).try_inverse().unwrap(),
+            blend.inverse_at(0.5),
+            epsilon = 0.0001
+        );
+    }
+
+    #[test]
+    fn transform_builders_apply_steps_in_reading_order() {
+        let p = point(1., 0., 1.);
+        let t = Transform::identity()
+            .rotate_x(PI / 2.)
+            .scale(5., 5., 5.)
+            .translate(10., 5., 7.);
+        assert_tuple_eq!(point(15., 0., 7.), t.matrix() * p, epsilon = 0.0001);
+        assert_relative_eq!(
+            translation(10., 5., 7.) * scaling(5., 5., 5.) * rotation_x(PI / 2.),
+            t.matrix(),
+            epsilon = 0.00001
+        );
+
+        let t = Transform::identity()
+            .shear(1., 0., 0., 0., 0., 0.)
+            .rotate(vec(0., 1., 1.), 0.3)
+            .rotate_z(-0.2)
+            .rotate_y(1.)
+            .scale_uniform(2.)
+            .then(translation(1., 2., 3.));
+        let expected = translation(1., 2., 3.)
+            * scaling(2., 2., 2.)
+            * rotation_y(1.)
+            * rotation_z(-0.2)
+            * rotation_axis(vec(0., 1., 1.), 0.3)
+            * shearing(1., 0., 0., 0., 0., 0.);
+        assert_relative_eq!(expected, Matrix4::from(t), epsilon = 0.00001);
+        assert_eq!(Matrix4::identity(), Transform::identity().matrix());
+    }
+
+    #[test]
+    fn transform_builders_can_be_used_in_place_of_matrices() {
+        let t = Transform::identity()
+            .scale_uniform(2.)
+            .translate(0., 1., 0.);
+
+        let s = Sphere::new(t, Default::default());
+        assert_eq!(Sphere::new(t.matrix(), Default::default()), s);
+
+        let c = Camera::new(
+            10,
+            10,
+            PI / 2.,
+            Transform::identity().translate(0., 0., -5.),
+        );
+        assert_eq!(translation(0., 0., -5.), c.view_transform());
+
+        let p = Stripe::new(white(), black(), Transform::identity().scale(0.5, 1., 1.));
+        assert_eq!(black(), p.sample_pattern_at(point(0.5, 0., 0.)));
+    }
+
+    #[test]
+    fn cached_transforms_hold_the_inverse_and_inverse_transpose() {
+        let m = translation(1., 2., 3.) * rotation_x(0.5) * scaling(1., 2., 4.);
+        let t = CachedTransform::new(m).unwrap();
+        assert_eq!(m, t.matrix());
+        assert_eq!(m.try_inverse().unwrap(), t.inverse());
+        assert_eq!(m.try_inverse().unwrap().transpose(), t.inverse_transpose());
+        assert_eq!(
+            CachedTransform::new(Matrix4::identity()).unwrap(),
+            CachedTransform::identity()
+        );
+
+        assert!(CachedTransform::new(scaling(1., 0., 1.)).is_none());
+    }
 }
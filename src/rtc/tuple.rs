@@ -1,51 +1,66 @@
+use crate::*;
 extern crate float_cmp;
-use self::float_cmp::{ApproxEq, F32Margin};
+use self::float_cmp::ApproxEq;
 
 use std::ops;
 
-#[derive(Debug, Copy, Clone)]
-pub struct Tuple {
-    pub x: f32,
-    pub y: f32,
-    pub z: f32,
-    pub w: f32,
+// points, vectors and surface normals are separate types (rather than 4-tuples
+// with a w component of 1 or 0) so that only the operations which make sense
+// will compile: point - point = vector, point + vector = point, but not point + point.
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Point3 {
+    pub x: Float,
+    pub y: Float,
+    pub z: Float,
 }
 
-impl Tuple {
-    pub fn new(x: f32, y: f32, z: f32, w: f32) -> Tuple {
-        Tuple {
-            x: x,
-            y: y,
-            z: z,
-            w: w,
-        }
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Vector3 {
+    pub x: Float,
+    pub y: Float,
+    pub z: Float,
+}
+
+// a direction perpendicular to a surface. Unlike vectors, normals need transforming
+// by the inverse transpose of a matrix to stay perpendicular to the transformed surface.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Normal3 {
+    pub x: Float,
+    pub y: Float,
+    pub z: Float,
+}
+
+impl Point3 {
+    pub fn new(x: Float, y: Float, z: Float) -> Point3 {
+        Point3 { x, y, z }
     }
 
-    pub fn is_point(&self) -> bool {
-        // TODO: should we be forcing w back to exactly 0 / 1?
-        // approx_eq!(1.0, self.w) // TODO
-        (self.w - 1.0).abs() < 0.0001
+    pub fn origin() -> Point3 {
+        Point3::new(0., 0., 0.)
     }
+}
 
-    pub fn is_vec(&self) -> bool {
-        // approx_eq!(1.0, self.w) // TODO
-        (self.w).abs() < 0.0001
+impl Vector3 {
+    pub fn new(x: Float, y: Float, z: Float) -> Vector3 {
+        Vector3 { x, y, z }
     }
 
-    pub fn magnitude(&self) -> f32 {
-        (self.x.powf(2.0) + self.y.powf(2.0) + self.z.powf(2.0)).sqrt()
+    pub fn magnitude(&self) -> Float {
+        self.dot(*self).sqrt()
     }
 
-    pub fn normalize(&self) -> Tuple {
+    pub fn normalize(&self) -> Vector3 {
         self / self.magnitude()
     }
 
-    pub fn dot(self, other: Tuple) -> f32 {
-        self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w
+    // works with normals too, since both are directions
+    pub fn dot<V: Into<Vector3>>(self, other: V) -> Float {
+        let other = other.into();
+        self.x * other.x + self.y * other.y + self.z * other.z
     }
 
-    pub fn cross(&self, other: &Tuple) -> Tuple {
-        // only implementing 3d version
+    pub fn cross(&self, other: &Vector3) -> Vector3 {
         vec(
             self.y * other.z - self.z * other.y,
             self.z * other.x - self.x * other.z,
@@ -54,87 +69,125 @@ impl Tuple {
     }
 }
 
-pub fn point(x: f32, y: f32, z: f32) -> Tuple {
-    Tuple::new(x, y, z, 1.0)
+impl Normal3 {
+    pub fn new(x: Float, y: Float, z: Float) -> Normal3 {
+        Normal3 { x, y, z }
+    }
+
+    pub fn magnitude(&self) -> Float {
+        Vector3::from(*self).magnitude()
+    }
+
+    pub fn normalize(&self) -> Normal3 {
+        Vector3::from(*self).normalize().into()
+    }
+
+    pub fn dot<V: Into<Vector3>>(self, other: V) -> Float {
+        Vector3::from(self).dot(other)
+    }
+
+    // transforms the normal of a surface which has been transformed by some matrix M.
+    // This takes the inverse of M (eg a shape's world to object transform), since
+    // that's usually already around, and multiplies by its transpose.
+    pub fn transform_by_inverse(self, inverse: &Matrix4) -> Normal3 {
+        self.transform_by_inverse_transpose(&inverse.transpose())
+    }
+
+    // the same, for when the inverse transpose has already been worked out
+    pub fn transform_by_inverse_transpose(self, m: &Matrix4) -> Normal3 {
+        Normal3::new(
+            m[(0, 0)] * self.x + m[(0, 1)] * self.y + m[(0, 2)] * self.z,
+            m[(1, 0)] * self.x + m[(1, 1)] * self.y + m[(1, 2)] * self.z,
+            m[(2, 0)] * self.x + m[(2, 1)] * self.y + m[(2, 2)] * self.z,
+        )
+    }
 }
 
-pub fn vec(x: f32, y: f32, z: f32) -> Tuple {
-    Tuple::new(x, y, z, 0.0)
+impl From<Normal3> for Vector3 {
+    fn from(n: Normal3) -> Vector3 {
+        vec(n.x, n.y, n.z)
+    }
 }
 
-impl PartialEq for Tuple {
-    fn eq(&self, other: &Tuple) -> bool {
-        self.x == other.x && self.y == other.y && self.z == other.z && self.w == other.w
+impl From<Vector3> for Normal3 {
+    fn from(v: Vector3) -> Normal3 {
+        Normal3::new(v.x, v.y, v.z)
     }
 }
 
-/// some magic to make ApproxEq work for Tuple
-/// based on https://docs.rs/float-cmp/0.6.0/float_cmp/index.html
-impl ApproxEq for Tuple {
-    type Margin = F32Margin;
+pub fn point(x: Float, y: Float, z: Float) -> Point3 {
+    Point3::new(x, y, z)
+}
 
-    fn approx_eq<T: Into<Self::Margin>>(self, other: Self, margin: T) -> bool {
-        let margin = margin.into();
-        self.x.approx_eq(other.x, margin)
-            && self.y.approx_eq(other.y, margin)
-            && self.z.approx_eq(other.z, margin)
-            && self.w.approx_eq(other.w, margin)
-    }
+pub fn vec(x: Float, y: Float, z: Float) -> Vector3 {
+    Vector3::new(x, y, z)
 }
 
+/// some magic to make ApproxEq work for our types
+/// based on https://docs.rs/float-cmp/0.6.0/float_cmp/index.html
+macro_rules! impl_approx_eq {
+    ($t:ty) => {
+        impl ApproxEq for $t {
+            type Margin = FloatMargin;
+
+            fn approx_eq<T: Into<Self::Margin>>(self, other: Self, margin: T) -> bool {
+                let margin = margin.into();
+                self.x.approx_eq(other.x, margin)
+                    && self.y.approx_eq(other.y, margin)
+                    && self.z.approx_eq(other.z, margin)
+            }
+        }
+    };
+}
+impl_approx_eq!(Point3);
+impl_approx_eq!(Vector3);
+impl_approx_eq!(Normal3);
+
 // using https://docs.rs/impl_ops/0.1.1/impl_ops/index.html to avoid lots of boilerplate here
-impl_op_ex!(+|a:&Tuple, b:&Tuple| -> Tuple { 
-    Tuple::new(a.x + b.x, a.y + b.y, a.z + b.z, a.w + b.w)
-});
-impl_op_ex!(-|a: &Tuple, b: &Tuple| -> Tuple {
-    Tuple::new(a.x - b.x, a.y - b.y, a.z - b.z, a.w - b.w)
-});
-impl_op_ex!(*|a: &Tuple, b: f32| -> Tuple { Tuple::new(a.x * b, a.y * b, a.z * b, a.w * b) });
-impl_op_ex!(/|a: &Tuple, b: f32| -> Tuple {
-    Tuple::new(a.x / b, a.y / b, a.z / b, a.w / b)
-});
-impl_op_ex!(-|a: &Tuple| -> Tuple { Tuple::new(-a.x, -a.y, -a.z, -a.w) });
+impl_op_ex!(-|a: &Point3, b: &Point3| -> Vector3 { vec(a.x - b.x, a.y - b.y, a.z - b.z) });
+impl_op_ex!(+|a: &Point3, b: &Vector3| -> Point3 { point(a.x + b.x, a.y + b.y, a.z + b.z) });
+impl_op_ex!(-|a: &Point3, b: &Vector3| -> Point3 { point(a.x - b.x, a.y - b.y, a.z - b.z) });
+
+impl_op_ex!(+|a: &Vector3, b: &Vector3| -> Vector3 { vec(a.x + b.x, a.y + b.y, a.z + b.z) });
+impl_op_ex!(-|a: &Vector3, b: &Vector3| -> Vector3 { vec(a.x - b.x, a.y - b.y, a.z - b.z) });
+impl_op_ex!(*|a: &Vector3, b: Float| -> Vector3 { vec(a.x * b, a.y * b, a.z * b) });
+impl_op_ex!(/|a: &Vector3, b: Float| -> Vector3 { vec(a.x / b, a.y / b, a.z / b) });
+impl_op_ex!(-|a: &Vector3| -> Vector3 { vec(-a.x, -a.y, -a.z) });
+
+// scaling a normal gives an offset along it, eg to move a point off of a surface
+impl_op_ex!(*|a: &Normal3, b: Float| -> Vector3 { vec(a.x * b, a.y * b, a.z * b) });
+impl_op_ex!(-|a: &Normal3| -> Normal3 { Normal3::new(-a.x, -a.y, -a.z) });
 
 #[cfg(test)]
 mod tests {
     use self::float_cmp::approx_eq;
     use super::*;
 
-    #[test]
-    fn tuple_creation() {
-        let a = point(4.3, -4.2, 3.1);
-        assert!(a.is_point());
-        assert!(!a.is_vec());
-
-        let b = vec(4.3, -4.2, 3.1);
-        assert!(!b.is_point());
-        assert!(b.is_vec());
-    }
-
     #[test]
     fn approx_equality() {
         // using example from https://docs.rs/float-cmp/0.6.0/float_cmp/index.html#the-problem
         // for some reason 0.1+0.2==0.3 works in rust
-        let x: f32 = 0.15 + 0.15 + 0.15;
-        let y: f32 = 0.1 + 0.1 + 0.25;
+        let x: Float = 0.15 + 0.15 + 0.15;
+        let y: Float = 0.1 + 0.1 + 0.25;
         let a = point(x, x, x);
         let b = point(y, y, y);
 
         assert_ne!(a, b);
-        assert!(approx_eq!(Tuple, a, b))
+        assert!(approx_eq!(Point3, a, b))
     }
 
     #[test]
-    fn adding_tuples() {
-        let a = Tuple::new(3.0, -2.0, 5.0, 1.0);
-        let b = Tuple::new(-2.0, 3.0, 1.0, 0.0);
+    fn adding_a_vector_to_a_point() {
+        let a = point(3.0, -2.0, 5.0);
+        let b = vec(-2.0, 3.0, 1.0);
 
         // adding a point and a vector gives a point
-        assert_eq!(Tuple::new(1.0, 1.0, 6.0, 1.0), a + b)
+        assert_eq!(point(1.0, 1.0, 6.0), a + b);
+        assert_eq!(point(5.0, -5.0, 4.0), a - b);
     }
 
     #[test]
-    fn subtracting_tuples() {
+    fn subtracting_points() {
         let a = point(3.0, 2.0, 1.0);
         let b = point(5.0, 6.0, 7.0);
 
@@ -143,30 +196,35 @@ mod tests {
     }
 
     #[test]
-    fn negating_tuples() {
-        // negating a tuple doesn't negate the w value
-        assert_eq!(-point(1.0, 2.0, 3.0), Tuple::new(-1.0, -2.0, -3.0, -1.0));
-        assert_eq!(-vec(1.0, 2.0, 3.0), vec(-1.0, -2.0, -3.0));
+    fn adding_and_negating_vectors() {
+        assert_eq!(
+            vec(1.0, 1.0, 6.0),
+            vec(3.0, -2.0, 5.0) + vec(-2.0, 3.0, 1.0)
+        );
+        assert_eq!(vec(-1.0, -2.0, -3.0), -vec(1.0, 2.0, 3.0));
+        assert_eq!(Normal3::new(-1.0, 0.0, 0.0), -Normal3::new(1.0, 0.0, 0.0));
     }
 
     #[test]
     fn scalar_multiplication_division() {
-        let a = Tuple::new(1.0, -2.0, 3.0, -4.0);
+        let a = vec(1.0, -2.0, 3.0);
 
-        assert_eq!(Tuple::new(3.5, -7.0, 10.5, -14.0), &a * 3.5);
-        assert_eq!(Tuple::new(0.5, -1.0, 1.5, -2.0), &a * 0.5);
-        assert_eq!(Tuple::new(0.5, -1.0, 1.5, -2.0), &a / 2.0);
+        assert_eq!(vec(3.5, -7.0, 10.5), &a * 3.5);
+        assert_eq!(vec(0.5, -1.0, 1.5), &a * 0.5);
+        assert_eq!(vec(0.5, -1.0, 1.5), &a / 2.0);
+
+        // scaling a normal gives a vector along it
+        assert_eq!(vec(0.0, 2.0, 0.0), Normal3::new(0.0, 1.0, 0.0) * 2.0);
     }
 
     #[test]
-    fn tuple_magnitude() {
+    fn vector_magnitude() {
         assert_eq!(1.0, vec(1.0, 0.0, 0.0).magnitude());
         assert_eq!(1.0, vec(0.0, 1.0, 0.0).magnitude());
         assert_eq!(1.0, vec(0.0, 0.0, 1.0).magnitude());
-        assert_eq!(1.0, point(0.0, 0.0, 1.0).magnitude());
 
-        assert_eq!(14.0_f32.sqrt(), vec(1.0, 2.0, 3.0).magnitude());
-        assert_eq!(14.0_f32.sqrt(), vec(-1.0, -2.0, -3.0).magnitude());
+        assert_eq!(Float::sqrt(14.0), vec(1.0, 2.0, 3.0).magnitude());
+        assert_eq!(Float::sqrt(14.0), vec(-1.0, -2.0, -3.0).magnitude());
     }
 
     #[test]
@@ -174,8 +232,13 @@ mod tests {
         let a = vec(1.0, 1.0, 1.0);
         let b = vec(1.0, 2.0, 3.0);
 
-        assert!(approx_eq!(f32, 1.0, a.normalize().magnitude()));
-        assert!(approx_eq!(f32, 1.0, b.normalize().magnitude()));
+        assert!(approx_eq!(Float, 1.0, a.normalize().magnitude()));
+        assert!(approx_eq!(Float, 1.0, b.normalize().magnitude()));
+        assert!(approx_eq!(
+            Float,
+            1.0,
+            Normal3::new(1.0, 2.0, 3.0).normalize().magnitude()
+        ));
     }
 
     #[test]
@@ -183,7 +246,8 @@ mod tests {
         let a = vec(1.0, 2.0, 3.0);
         let b = vec(2.0, 3.0, 4.0);
 
-        assert_eq!(20.0, Tuple::dot(a, b))
+        assert_eq!(20.0, Vector3::dot(a, b));
+        assert_eq!(20.0, a.dot(Normal3::new(2.0, 3.0, 4.0)));
     }
 
     #[test]
@@ -191,7 +255,21 @@ mod tests {
         let a = vec(1.0, 2.0, 3.0);
         let b = vec(2.0, 3.0, 4.0);
 
-        assert_eq!(vec(-1.0, 2.0, -1.0), Tuple::cross(&a, &b));
-        assert_eq!(vec(1.0, -2.0, 1.0), Tuple::cross(&b, &a));
+        assert_eq!(vec(-1.0, 2.0, -1.0), Vector3::cross(&a, &b));
+        assert_eq!(vec(1.0, -2.0, 1.0), Vector3::cross(&b, &a));
+    }
+
+    #[test]
+    fn normals_are_transformed_by_the_inverse_transpose() {
+        // squashing a sphere in y makes its normals point more steeply up
+        let transform = scaling(1., 0.5, 1.);
+        let inverse = transform.try_inverse().unwrap();
+        let s22 = Float::sqrt(2.) / 2.;
+        let n = Normal3::new(s22, s22, 0.).transform_by_inverse(&inverse);
+        assert_tuple_eq!(Normal3::new(s22, 2. * s22, 0.), n);
+
+        // translations don't affect normals
+        let n = Normal3::new(0., 1., 0.).transform_by_inverse(&translation(1., 2., 3.));
+        assert_tuple_eq!(Normal3::new(0., 1., 0.), n);
     }
 }